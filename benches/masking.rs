@@ -0,0 +1,52 @@
+//! Run: cargo bench --bench masking
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use tokio::runtime::Runtime;
+use web_socket::{Frame, ReadOnly, WebSocket};
+
+const SIZES: [usize; 4] = [128, 4 * 1024, 64 * 1024, 1024 * 1024];
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    for size in SIZES {
+        let data = vec![0xab; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &data, |b, data| {
+            b.iter(|| Frame::from(&data[..]).encode_with([1, 2, 3, 4]))
+        });
+    }
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("decode");
+    for size in SIZES {
+        let frame = Frame::from(&vec![0xab; size][..]).encode_with([1, 2, 3, 4]);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &frame, |b, frame| {
+            b.iter(|| {
+                rt.block_on(WebSocket::server(ReadOnly(&frame[..])).recv())
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn client_send(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("client_send");
+    let mut ws = WebSocket::client(tokio::io::sink());
+    for size in SIZES {
+        let data = vec![0xab; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &data, |b, data| {
+            b.iter(|| rt.block_on(ws.send(&data[..])).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode, client_send);
+criterion_main!(benches);
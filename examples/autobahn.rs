@@ -1,13 +1,13 @@
-mod utils;
-
-use std::{error::Error, str};
-use tokio::{io::*, net::TcpListener};
-use utils::*;
-use web_socket::*;
+use std::{error::Error, str, sync::Arc};
+use tokio::{
+    io::*,
+    net::{TcpListener, TcpStream},
+};
+use web_socket::{client, deflate::PerMessageDeflate, *};
 
 type Result<T = (), E = Box<dyn Error>> = std::result::Result<T, E>;
 
-const ADDR: &str = "localhost:9001";
+const ADDR: &str = "ws://localhost:9001";
 const AGENT: &str = "agent=web-socket";
 
 #[tokio::main(flavor = "current_thread")]
@@ -17,31 +17,32 @@ async fn main() -> Result {
         println!("[Echo Server] Listening at {addr}");
         loop {
             let (stream, _addr) = listener.accept().await?;
-            let mut stream = BufReader::new(stream);
-            let http = HttpRequest::parse(&mut stream).await?;
-            let key = get_sec_key(&http).expect("invalid websocket request");
-
-            stream
-                .write_all(handshake::response(key, [("x-agent", "web-socket")]).as_bytes())
-                .await?;
-
             tokio::spawn(async {
-                let _ = echo(WebSocket::server(stream)).await;
+                let config = handshake::Config {
+                    extensions: vec![Arc::new(PerMessageDeflate::default())],
+                    ..Default::default()
+                };
+                if let Ok(ws) = handshake::accept_with(stream, &config).await {
+                    let _ = echo(ws).await;
+                }
             });
         }
-        // echo(connect(ADDR, &path).await?);
     } else {
-        let total: u32 = match connect(ADDR, "/getCaseCount").await?.recv().await? {
+        let total: u32 = match connect(&format!("{ADDR}/getCaseCount"))
+            .await?
+            .recv()
+            .await?
+        {
             Event::Data { data, .. } => str::from_utf8(&data)?.parse()?,
             _ => return Err("unable to get case count".into()),
         };
 
         for case in 1..=total {
-            let path = format!("/runCase?case={case}&{AGENT}");
-            let _ = echo(connect(ADDR, &path).await?).await;
+            let url = format!("{ADDR}/runCase?case={case}&{AGENT}");
+            let _ = echo(connect(&url).await?).await;
         }
 
-        connect(ADDR, &format!("/updateReports?{AGENT}"))
+        connect(&format!("{ADDR}/updateReports?{AGENT}"))
             .await?
             .close(())
             .await?;
@@ -50,26 +51,24 @@ async fn main() -> Result {
     Ok(())
 }
 
+async fn connect(url: &str) -> Result<WebSocket<TcpStream>, handshake::Error> {
+    let config = client::Config {
+        extensions: vec![Arc::new(PerMessageDeflate::default())],
+        ..Default::default()
+    };
+    client::connect_with(url, &config).await
+}
+
 pub async fn echo<IO>(mut ws: WebSocket<IO>) -> Result
 where
     IO: AsyncRead + AsyncWrite + Unpin,
 {
-    let mut buf = Vec::with_capacity(4096);
     loop {
-        match ws.recv_event().await? {
-            Event::Data { ty, data } => match ty {
-                DataType::Complete(ty) => send_msg(&mut ws, ty, &data).await?,
-                DataType::Stream(stream) => {
-                    buf.extend_from_slice(&data);
-                    if let Stream::End(ty) = stream {
-                        send_msg(&mut ws, ty, &buf).await?;
-                        buf.clear();
-                    }
-                }
-            },
+        match ws.recv_message().await? {
+            Event::Data { ty, data, .. } => send_msg(&mut ws, ty.ty(), &data).await?,
             Event::Ping(data) => ws.send_pong(data).await?,
             Event::Pong(_) => {}
-            Event::Error(_) => break ws.close(CloseCode::ProtocolError).await?,
+            Event::Error(err) => break ws.close(err).await?,
             Event::Close { .. } => break ws.close(()).await?,
         }
     }
@@ -8,36 +8,59 @@ use axum::{
 use hyper::upgrade::Upgraded;
 use hyper_util::rt::TokioIo;
 use std::future::Future;
+use web_socket::handshake::Negotiated;
 
 pub type WebSocket = web_socket::WebSocket<TokioIo<Upgraded>>;
 pub use web_socket;
 
 pub struct WebSocketUpgrade {
     sec_websocket_key: HeaderValue,
+    sec_websocket_protocol: Vec<String>,
+    protocol: Option<String>,
     on_upgrade: hyper::upgrade::OnUpgrade,
 }
 
 impl WebSocketUpgrade {
+    /// Select the first subprotocol (in order of preference) that is also offered by the client.
+    pub fn protocols<I>(mut self, protocols: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.protocol = protocols
+            .into_iter()
+            .map(Into::into)
+            .find(|protocol| self.sec_websocket_protocol.contains(protocol));
+        self
+    }
+
     pub fn on_upgrade<C, Fut>(self, callback: C) -> Response
     where
         C: FnOnce(WebSocket) -> Fut + Send + 'static,
         Fut: Future<Output = ()> + Send + 'static,
     {
-        tokio::spawn(async move {
-            if let Ok(upgraded) = self.on_upgrade.await {
-                callback(WebSocket::server(TokioIo::new(upgraded))).await;
-            }
-        });
-        Response::builder()
+        let negotiated = Negotiated {
+            protocol: self.protocol,
+            ..Default::default()
+        };
+        let mut response = Response::builder()
             .status(StatusCode::SWITCHING_PROTOCOLS)
             .header(header::CONNECTION, HeaderValue::from_static("upgrade"))
             .header(header::UPGRADE, HeaderValue::from_static("websocket"))
             .header(
                 header::SEC_WEBSOCKET_ACCEPT,
                 sign(self.sec_websocket_key.as_bytes()),
-            )
-            .body(axum::body::Body::empty())
-            .unwrap()
+            );
+        if let Some(protocol) = &negotiated.protocol {
+            response = response.header(header::SEC_WEBSOCKET_PROTOCOL, protocol);
+        }
+        tokio::spawn(async move {
+            if let Ok(upgraded) = self.on_upgrade.await {
+                let ws = WebSocket::server(TokioIo::new(upgraded)).with_negotiated(negotiated);
+                callback(ws).await;
+            }
+        });
+        response.body(axum::body::Body::empty()).unwrap()
     }
 }
 
@@ -67,6 +90,15 @@ where
                 .get(header::SEC_WEBSOCKET_KEY)
                 .ok_or(())?
                 .clone(),
+            sec_websocket_protocol: parts
+                .headers
+                .get_all(header::SEC_WEBSOCKET_PROTOCOL)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .map(|protocol| protocol.trim().to_string())
+                .collect(),
+            protocol: None,
             on_upgrade: parts
                 .extensions
                 .remove::<hyper::upgrade::OnUpgrade>()
@@ -76,14 +108,8 @@ where
 }
 
 fn sign(key: &[u8]) -> HeaderValue {
-    use base64::engine::Engine as _;
-    use sha1::{Digest, Sha1};
-
-    let mut sha1 = Sha1::default();
-    sha1.update(key);
-    sha1.update(&b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11"[..]);
-    let b64 = Bytes::from(base64::engine::general_purpose::STANDARD.encode(sha1.finalize()));
-    HeaderValue::from_maybe_shared(b64).expect("base64 is a valid value")
+    let key = web_socket::handshake::accept_key_from(key);
+    HeaderValue::from_maybe_shared(Bytes::from(key)).expect("base64 is a valid value")
 }
 
 fn header_eq(headers: &HeaderMap, key: HeaderName, value: &'static str) -> bool {
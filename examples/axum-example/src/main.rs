@@ -27,7 +27,7 @@ async fn handle_socket(mut socket: WebSocket, who: SocketAddr) {
 
     while let Ok(ev) = socket.recv().await {
         match ev {
-            Event::Data { ty, data } => {
+            Event::Data { ty, data, .. } => {
                 println!("DataType: {:?}", ty);
                 println!("Data: {:?}", String::from_utf8(data.to_vec()));
             }
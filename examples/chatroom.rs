@@ -5,12 +5,15 @@
 //!
 //! Run: cargo r -r --example chatroom -- 127.0.0.1:8080
 
-mod utils;
-
-use std::{collections::HashMap, error::Error, net::SocketAddr, str, sync::Arc};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    error::Error,
+    net::SocketAddr,
+    str,
+    sync::Arc,
+};
 use tokio::{io::*, net::TcpListener, select, sync::mpsc};
-use utils::{handshake, HttpRequest};
-use web_socket::*;
+use web_socket::{handshake::Request, *};
 
 type Result<T = (), E = Box<dyn Error>> = std::result::Result<T, E>;
 type Sender<T> = mpsc::UnboundedSender<T>;
@@ -22,7 +25,7 @@ async fn main() -> Result {
     println!("[Server] Listening at {addr}");
     println!("Goto: http://localhost:8080/");
 
-    let mut room: HashMap<SocketAddr, Sender<Message>> = HashMap::new();
+    let mut room: HashMap<SocketAddr, Sender<Arc<str>>> = HashMap::new();
     let (cmd_tx, mut cmd_rx) = mpsc::unbounded_channel::<Command>();
 
     // Because both `tokio::sync::mpsc::Receiver::recv` and `tokio::net::TcpListener::accept` are cancellation safe. Its ok to use `select!` in main loop.
@@ -51,57 +54,47 @@ async fn main() -> Result {
                 let (reader, mut writer) = stream.into_split();
                 let mut reader = BufReader::new(reader);
 
-                let req = HttpRequest::parse(&mut reader).await?;
-                if req.prefix.starts_with("GET / HTTP/1.1") {
+                let req = Request::parse(&mut reader).await?;
+                if req.method == "GET" && req.path == "/" {
                     let content = include_str!("./assets/chatroom.html");
                     let content_len = content.len();
                     let response = format!("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {content_len}\r\n\r\n{content}");
                     writer.write_all(response.as_bytes()).await?;
-                }
-                if let Some(key) = utils::get_sec_key(&req) {
-                    let res = handshake::response(key, [("x-agent", "web-socket")]);
+                } else if handshake::reply(&mut writer, &req).await.is_ok() {
                     println!("From: {addr}\n{req:#?}");
-                    println!("\nTo: {addr}\n{res}");
-                    writer.write_all(res.as_bytes()).await?;
 
-                    let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+                    let (tx, rx) = mpsc::unbounded_channel::<Arc<str>>();
                     room.insert(addr, tx.clone());
 
-                    let mut ws = WebSocket::server(writer);
-                    tokio::spawn(async move {
-                        let _ = handle_client(cmd_tx, addr, tx, WebSocket::server(reader)).await;
-                    });
+                    // Frames that arrived along with the request are already buffered in `reader`.
+                    let buffered = reader.buffer().to_vec();
+                    let stream = reader.into_inner().reunite(writer)?;
+                    let ws = WebSocket::from_parts(stream, Role::Server, buffered);
+
                     tokio::spawn(async move {
-                        while let Some(msg) = rx.recv().await {
-                            let _ = match msg {
-                                Message::Ping(data) => ws.send_pong(data).await,
-                                Message::Reply(msg) => ws.send(&*msg).await,
-                            };
-                        }
+                        let _ = handle_client(cmd_tx, addr, tx, rx, ws).await;
                     });
                 }
             }
             Some(cmd) = cmd_rx.recv() => {
                 match cmd {
                     Command::JoinRoom { user_addr, user_tx } => {
-                        if room.get(&user_addr).is_none() {
-                            room.insert(user_addr, user_tx);
+                        if let Entry::Vacant(entry) = room.entry(user_addr) {
+                            entry.insert(user_tx);
                             for user_tx in room.values() {
-                                let _ = user_tx.send(Message::Reply(format!("New User: {user_addr}").into()));
+                                let _ = user_tx.send(format!("New User: {user_addr}").into());
                             }
                         }
                     },
                     Command::RemoveUser { user_addr } => {
                         room.remove(&user_addr);
                         for user_tx in room.values() {
-                            let _ = user_tx.send(Message::Reply(
-                                format!("User: {user_addr} Disconnected").into(),
-                            ));
+                            let _ = user_tx.send(format!("User: {user_addr} Disconnected").into());
                         }
                     },
                     Command::Send(msg) => {
                         for user_tx in room.values() {
-                            let _ = user_tx.send(Message::Reply(msg.clone()));
+                            let _ = user_tx.send(msg.clone());
                         }
                     },
 
@@ -114,7 +107,7 @@ async fn main() -> Result {
 enum Command {
     JoinRoom {
         user_addr: SocketAddr,
-        user_tx: Sender<Message>,
+        user_tx: Sender<Arc<str>>,
     },
     RemoveUser {
         user_addr: SocketAddr,
@@ -123,46 +116,48 @@ enum Command {
     Send(Arc<str>),
 }
 
-enum Message {
-    Reply(Arc<str>),
-    Ping(Box<[u8]>),
-}
-
-async fn handle_client<R>(
+/// `WebSocket::recv` is cancellation safe, So a single task can receive websocket events and replies with `select!`.
+async fn handle_client<IO>(
     cmd: Sender<Command>,
     addr: SocketAddr,
-    tx: Sender<Message>,
-    mut ws: WebSocket<R>,
+    tx: Sender<Arc<str>>,
+    mut rx: mpsc::UnboundedReceiver<Arc<str>>,
+    mut ws: WebSocket<IO>,
 ) -> Result
 where
-    R: AsyncRead + Send + Unpin + 'static,
+    IO: AsyncRead + AsyncWrite + Send + Unpin + 'static,
 {
     cmd.send(Command::Send(format!("New User: {addr}").into()))?;
-    while let Ok(ev) = ws.recv().await {
-        match ev {
-            Event::Data { data, .. } => {
-                let (kind, data) = str::from_utf8(&data)?.split_once(":").unwrap_or_default();
-                match kind {
-                    "Echo" => tx.send(Message::Reply(data.into()))?,
-
-                    "Send" => cmd.send(Command::Send(
-                        format!("From: {addr}, Message: {data}").into(),
-                    ))?,
-
-                    "JoinRoom" => cmd.send(Command::JoinRoom {
-                        user_tx: tx.clone(),
-                        user_addr: addr,
-                    })?,
-
-                    "NOOP" => {}
-                    _unknown_command => {}
+    loop {
+        select! {
+            ev = ws.recv() => match ev {
+                Ok(Event::Data { data, .. }) => {
+                    let (kind, data) = str::from_utf8(&data)?.split_once(":").unwrap_or_default();
+                    match kind {
+                        "Echo" => tx.send(data.into())?,
+
+                        "Send" => cmd.send(Command::Send(
+                            format!("From: {addr}, Message: {data}").into(),
+                        ))?,
+
+                        "JoinRoom" => cmd.send(Command::JoinRoom {
+                            user_tx: tx.clone(),
+                            user_addr: addr,
+                        })?,
+
+                        "NOOP" => {}
+                        _unknown_command => {}
+                    }
                 }
-            }
-            Event::Ping(msg) => tx.send(Message::Ping(msg))?,
-            Event::Pong(_) => {}
-            Event::Error(_) | Event::Close { .. } => break,
+                Ok(Event::Ping(data)) => ws.send_pong(data).await?,
+                Ok(Event::Pong(_)) => {}
+                Ok(Event::Error(_) | Event::Close { .. }) | Err(_) => break,
+            },
+            Some(msg) = rx.recv() => ws.send(&*msg).await?,
         }
     }
     cmd.send(Command::RemoveUser { user_addr: addr })?;
+    // The reply to a received `Close` frame is already sent by `recv`, So this only shuts down the stream.
+    let _ = ws.close(()).await;
     Ok(())
 }
@@ -0,0 +1,28 @@
+//! Websocket client for targets without an operating system.
+//!
+//! Build it with: `cargo build -p embedded-example --target thumbv7em-none-eabihf`
+#![no_std]
+
+use embedded_io_async::{Read, Write};
+use web_socket::{embedded::WebSocket, Event};
+
+/// Sends `Hello`, And echoes received messages back, Until the connection is closed.
+pub async fn echo<IO: Read + Write>(stream: IO) -> web_socket::io::Result<()> {
+    let mut ws = WebSocket::client(stream);
+    ws.send("Hello").await?;
+    loop {
+        match ws.recv().await? {
+            Event::Data { data, .. } => ws.send(&*data).await?,
+            Event::Ping(data) => ws.send_pong(data).await?,
+            Event::Pong(_) => {}
+            Event::Error(_) | Event::Close { .. } => return ws.close(()).await,
+        }
+    }
+}
+
+/// Client masking-keys are read from `getrandom`, Fill `buf` from the hardware RNG of the microcontroller here.
+fn hardware_rng(_buf: &mut [u8]) -> Result<(), getrandom::Error> {
+    Err(getrandom::Error::UNSUPPORTED)
+}
+
+getrandom::register_custom_getrandom!(hardware_rng);
@@ -1,11 +1,9 @@
-mod utils;
-
 use tokio::io::*;
 use web_socket::*;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    example(utils::connect("ws.ifelse.io:80", "/").await?).await
+    example(client::connect("ws://ws.ifelse.io").await?).await
 }
 
 async fn example<IO>(mut ws: WebSocket<IO>) -> Result<()>
@@ -16,13 +14,13 @@ where
     for _ in 0..3 {
         ws.send("Copy Cat!").await?;
         match ws.recv().await? {
-            Event::Data { ty, data } => {
+            Event::Data { ty, data, .. } => {
                 assert!(matches!(ty, DataType::Complete(MessageType::Text)));
                 assert_eq!(&*data, b"Copy Cat!");
             }
             Event::Ping(data) => ws.send_pong(data).await?,
             Event::Pong(..) => {}
-            Event::Error(..) => return ws.close(CloseCode::ProtocolError).await,
+            Event::Error(err) => return ws.close(err).await,
             Event::Close { .. } => return ws.close(()).await,
         }
     }
@@ -0,0 +1,118 @@
+//! Blocking websocket over [std::io::Read] and [std::io::Write] streams.
+//!
+//! Every call blocks the thread, Until the frames are written or an event is received. Frames are written immediately, There is no write queue.
+//! With a read timeout (or a non-blocking stream), `recv` returns the error of the stream, And the next call resumes where it stopped.
+//!
+//! ### Example
+//!
+//! ```no_run
+//! use web_socket::{blocking::WebSocket, Event};
+//!
+//! # fn run(stream: std::net::TcpStream) -> std::io::Result<()> {
+//! let mut ws = WebSocket::server(stream);
+//! loop {
+//!     match ws.recv()? {
+//!         Event::Data { data, .. } => ws.send(&*data)?,
+//!         Event::Ping(data) => ws.send_pong(data)?,
+//!         Event::Pong(_) => {}
+//!         Event::Error(_) | Event::Close { .. } => return ws.close(()),
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::*;
+use std::io::{ErrorKind, Read, Result, Write};
+
+/// Blocking websocket implementation for both client and server
+///
+/// Protocol state and its configuration (For example: [Connection::max_payload_len]) are accessible through `Deref`.
+#[derive(Debug)]
+pub struct WebSocket<Stream> {
+    /// Underlying byte stream over which websocket messages are exchanged.
+    pub stream: Stream,
+    conn: Connection,
+    /// Received `Close` (or `Error`) event, That is returned once the `Close` frame is written in reply.
+    closing: Option<Event>,
+}
+
+impl_websocket!(closing: None);
+
+impl<W: Write> WebSocket<W> {
+    impl_send!(blocking);
+
+    /// Write the encoded frames.
+    ///
+    /// Written bytes are tracked by the connection, So it resumes after an error. (For example: [ErrorKind::WouldBlock])
+    fn write_outgoing(&mut self) -> Result<()> {
+        loop {
+            let outgoing = self.conn.outgoing();
+            if outgoing.is_empty() {
+                return Ok(());
+            }
+            match self.stream.write(outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(amt) => self.conn.consume_outgoing(amt),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    #[inline]
+    fn flush_stream(&mut self) -> Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl<IO: Read + Write> WebSocket<IO> {
+    impl_recv! {
+        blocking;
+        /// reads [Event] from websocket stream.
+        ///
+        /// Partially received frame is kept in the websocket, So if the stream returns an error
+        /// Of [ErrorKind::WouldBlock] or [ErrorKind::TimedOut] (For example: a read timeout),
+        /// The next call resumes reading exactly where it stopped.
+        fn recv;
+    }
+
+    impl_close! {
+        blocking;
+        /// Set a read timeout on the stream (For example: [std::net::TcpStream::set_read_timeout]), To limit the wait.
+        /// `Write` has no way to shutdown a stream, So it is flushed. And closed once it is dropped.
+    }
+
+    #[inline]
+    fn wait_for_close(&mut self) -> Result<()> {
+        self.recv_close()
+    }
+
+    #[inline]
+    fn shutdown(&mut self) -> Result<()> {
+        self.stream.flush()
+    }
+
+    /// Read some bytes from the stream, Into the read buffer of the connection.
+    fn fill(&mut self) -> Result<()> {
+        loop {
+            match self.stream.read(self.conn.read_buf()) {
+                Ok(0) => {
+                    self.conn.fail();
+                    return Err(ErrorKind::UnexpectedEof.into());
+                }
+                Ok(amt) => {
+                    self.conn.advance(amt);
+                    return Ok(());
+                }
+                Err(err) => match err.kind() {
+                    ErrorKind::Interrupted => {}
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut => return Err(err),
+                    _ => {
+                        self.conn.fail();
+                        return Err(err);
+                    }
+                },
+            }
+        }
+    }
+}
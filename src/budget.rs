@@ -0,0 +1,125 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Limits the memory, that is used by websockets to buffer partially received frames and messages.
+///
+/// A budget can be shared between many connections, And it may have a parent budget.
+/// So that every connection has its own limit, and all the connections together has a global limit.
+///
+/// Frame (or message) that exceeds the budget is rejected with [ProtocolError::MessageTooLarge](crate::ProtocolError::MessageTooLarge),
+/// Which fails the connection with status code `1009`.
+///
+/// ### Example
+///
+/// ```rust
+/// # #[cfg(feature = "tokio")] {
+/// use web_socket::{MemoryBudget, WebSocket};
+///
+/// let global = MemoryBudget::new(512 * 1024 * 1024);
+///
+/// let mut ws = WebSocket::server(tokio::io::empty());
+/// ws.memory_budget = Some(MemoryBudget::with_parent(32 * 1024 * 1024, &global));
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: usize,
+    used: AtomicUsize,
+    parent: Option<Arc<MemoryBudget>>,
+}
+
+impl MemoryBudget {
+    /// Create a new budget of `limit` bytes.
+    pub fn new(limit: usize) -> Arc<Self> {
+        Arc::new(Self {
+            limit,
+            used: AtomicUsize::new(0),
+            parent: None,
+        })
+    }
+
+    /// Create a new budget of `limit` bytes, That also draws from the `parent` budget.
+    pub fn with_parent(limit: usize, parent: &Arc<Self>) -> Arc<Self> {
+        Arc::new(Self {
+            limit,
+            used: AtomicUsize::new(0),
+            parent: Some(parent.clone()),
+        })
+    }
+
+    /// Returns the maximum number of bytes, that can be used.
+    #[inline]
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the number of bytes, that are currently in use.
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Acquire)
+    }
+
+    fn try_acquire(&self, amt: usize) -> bool {
+        let acquired = self
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                used.checked_add(amt).filter(|&used| used <= self.limit)
+            })
+            .is_ok();
+
+        if acquired {
+            if let Some(parent) = &self.parent {
+                if !parent.try_acquire(amt) {
+                    self.used.fetch_sub(amt, Ordering::AcqRel);
+                    return false;
+                }
+            }
+        }
+        acquired
+    }
+
+    fn release(&self, amt: usize) {
+        self.used.fetch_sub(amt, Ordering::AcqRel);
+        if let Some(parent) = &self.parent {
+            parent.release(amt);
+        }
+    }
+}
+
+/// Bytes that are acquired from a [MemoryBudget], They are released on drop.
+#[derive(Debug, Default)]
+pub(crate) struct Reservation {
+    budget: Option<Arc<MemoryBudget>>,
+    len: usize,
+}
+
+impl Reservation {
+    /// Acquire `amt` more bytes from `budget`, Returns `false` if the budget is exceeded.
+    pub fn grow(&mut self, budget: &Option<Arc<MemoryBudget>>, amt: usize) -> bool {
+        if self.budget.is_none() {
+            match budget {
+                Some(budget) => self.budget = Some(budget.clone()),
+                None => return true,
+            }
+        }
+        let acquired = self.budget.as_ref().is_some_and(|b| b.try_acquire(amt));
+        if acquired {
+            self.len += amt;
+        }
+        acquired
+    }
+
+    /// Release all the acquired bytes.
+    pub fn clear(&mut self) {
+        if let Some(budget) = self.budget.take() {
+            budget.release(self.len);
+        }
+        self.len = 0;
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
@@ -0,0 +1,227 @@
+//! # Client handshake
+//!
+//! A client opens a connection and sends a handshake request, The server replies with `101 Switching Protocols`:
+//!
+//! ```yml
+//! GET /chat HTTP/1.1
+//! Host: example.com:8000
+//! Upgrade: websocket
+//! Connection: Upgrade
+//! Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ== # randomly generated
+//! Sec-WebSocket-Version: 13
+//! ```
+//!
+//! ### Example
+//!
+//! ```no_run
+//! # #[cfg(feature = "tokio")]
+//! # async fn run() -> std::io::Result<()> {
+//! let mut ws = web_socket::client::connect("ws://example.com/chat").await?;
+//! ws.send("Hello!").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+use crate::extension::Offer;
+use crate::{
+    extension::Negotiator,
+    handshake::{base64_encode, Header},
+    random_bytes,
+};
+#[cfg(feature = "tokio")]
+use crate::{
+    handshake::{Error, Response},
+    Role, WebSocket,
+};
+use alloc::{format, string::String, sync::Arc, vec::Vec};
+#[cfg(feature = "tokio")]
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+
+/// Create websocket handshake request, Returns the request and the `Sec-WebSocket-Key` header value.
+///
+/// `Sec-WebSocket-Key` is a randomly selected 16-byte nonce, base64-encoded.
+///
+/// ### Example
+///
+/// ```rust
+/// let (req, sec_key) = web_socket::client::request("example.com", "/path", [("key", "value")]);
+/// assert!(req.starts_with("GET /path HTTP/1.1\r\nHost: example.com\r\n"));
+/// assert!(req.contains(&format!("Sec-WebSocket-Key: {sec_key}\r\n")));
+/// assert!(req.ends_with("key: value\r\n\r\n"));
+/// ```
+///
+/// ### Output
+///
+/// ```yaml
+/// GET /path HTTP/1.1
+/// Host: example.com
+/// Upgrade: websocket
+/// Connection: Upgrade
+/// Sec-WebSocket-Version: 13
+/// Sec-WebSocket-Key: D3E1sFZlZfeZgNXtVHfhKg== # randomly generated
+/// key: value
+/// ...
+/// ```
+pub fn request(
+    host: impl AsRef<str>,
+    path: impl AsRef<str>,
+    headers: impl IntoIterator<Item = impl Header>,
+) -> (String, String) {
+    let host = host.as_ref();
+    let path = path.as_ref().trim_start_matches('/');
+    let sec_key = base64_encode(random_bytes::<16>());
+    let headers: String = headers.into_iter().map(|f| Header::fmt(&f)).collect();
+    (format!("GET /{path} HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Version: 13\r\nSec-WebSocket-Key: {sec_key}\r\n{headers}\r\n"), sec_key)
+}
+
+/// Client handshake options.
+#[derive(Default, Clone)]
+pub struct Config {
+    /// Subprotocols offered to the server with `Sec-WebSocket-Protocol` header, in order of preference.
+    pub protocols: Vec<String>,
+    /// Extensions offered to the server with `Sec-WebSocket-Extensions` header, in order of preference.
+    pub extensions: Vec<Arc<dyn Negotiator>>,
+    /// Additional request header fields, For example: `Origin`, `Cookie`, `Authorization` etc...
+    pub headers: Vec<(String, String)>,
+}
+
+impl Config {
+    /// Create handshake request with these options, Returns the request and the `Sec-WebSocket-Key` header value.
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    pub(crate) fn request(&self, host: &str, path: &str) -> (String, String) {
+        let protocols = self.protocols.join(", ");
+        let protocols =
+            Some(("Sec-WebSocket-Protocol", protocols.as_str())).filter(|_| !protocols.is_empty());
+        let offers: Vec<Offer> = self.extensions.iter().flat_map(|ext| ext.offer()).collect();
+        let extensions = Offer::to_header(&offers);
+        let extensions =
+            Some(("Sec-WebSocket-Extensions", extensions.as_str())).filter(|_| !offers.is_empty());
+
+        let headers = protocols
+            .into_iter()
+            .chain(extensions)
+            .chain(self.headers.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+
+        request(host, path, headers)
+    }
+}
+
+/// Perform client handshake over an established `stream`, Returns a websocket client instance.
+///
+/// If the server responds with a status code other than `101`, [Error::Rejected] is returned with the response (including the body, if any).
+///
+/// Any bytes that were read past the response head (for example, websocket frames that arrived in the same segment as the response)
+/// are not lost, The returned websocket reads them before reading from `stream`.
+#[cfg(feature = "tokio")]
+pub async fn handshake<IO>(stream: IO, host: &str, path: &str) -> Result<WebSocket<IO>, Error>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    handshake_with(stream, host, path, &Config::default()).await
+}
+
+/// Same as [handshake], But with additional options.
+///
+/// ### Example
+///
+/// ```no_run
+/// use web_socket::client::{self, Config};
+///
+/// # async fn run(stream: tokio::net::TcpStream) -> Result<(), web_socket::handshake::Error> {
+/// let config = Config {
+///     protocols: vec!["v2.chat".into(), "v1.chat".into()],
+///     ..Default::default()
+/// };
+///
+/// let ws = client::handshake_with(stream, "example.com", "/chat", &config).await?;
+/// println!("subprotocol: {:?}", ws.protocol());
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn handshake_with<IO>(
+    mut stream: IO,
+    host: &str,
+    path: &str,
+    config: &Config,
+) -> Result<WebSocket<IO>, Error>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    let (req, sec_key) = config.request(host, path);
+    stream.write_all(req.as_bytes()).await?;
+    stream.flush().await?;
+
+    let mut reader = BufReader::new(&mut stream);
+    let mut res = Response::parse(&mut reader).await?;
+    if res.status != 101 {
+        res.read_body(&mut reader).await?;
+        return Err(Error::Rejected(res));
+    }
+    let buffered = reader.buffer().to_vec();
+    let negotiated = res.validate(&sec_key, config)?;
+    Ok(WebSocket::from_parts(stream, Role::Client, buffered).with_negotiated(negotiated))
+}
+
+/// Connect to a websocket server at `url`, For example: `ws://example.com:8080/chat`
+///
+/// Only `ws` scheme is supported, For `wss` use [handshake] with a TLS stream.
+#[cfg(feature = "tokio")]
+pub async fn connect(url: &str) -> Result<WebSocket<TcpStream>, Error> {
+    connect_with(url, &Config::default()).await
+}
+
+/// Same as [connect], But with additional options.
+#[cfg(feature = "tokio")]
+pub async fn connect_with(url: &str, config: &Config) -> Result<WebSocket<TcpStream>, Error> {
+    let url = Url::parse(url)?;
+    let stream = TcpStream::connect((url.hostname(), url.port)).await?;
+    handshake_with(stream, url.host, url.path, config).await
+}
+
+#[cfg(feature = "tokio")]
+struct Url<'a> {
+    host: &'a str,
+    port: u16,
+    path: &'a str,
+}
+
+#[cfg(feature = "tokio")]
+impl<'a> Url<'a> {
+    fn parse(url: &'a str) -> Result<Self, Error> {
+        let rest = match url.split_once("://") {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("ws") => rest,
+            Some((scheme, _)) if scheme.eq_ignore_ascii_case("wss") => {
+                return Err(Error::InvalidUrl("`wss` scheme requires a TLS stream"))
+            }
+            _ => return Err(Error::InvalidUrl("expected `ws` scheme")),
+        };
+        let (host, path) = match rest.find(['/', '?']) {
+            Some(idx) => rest.split_at(idx),
+            None => (rest, "/"),
+        };
+        if host.is_empty() || host.contains('@') {
+            return Err(Error::InvalidUrl("invalid host"));
+        }
+        let port = match host.rsplit_once(':') {
+            Some((_, port)) if !host.ends_with(']') => port
+                .parse()
+                .map_err(|_| Error::InvalidUrl("invalid port"))?,
+            _ => 80,
+        };
+        Ok(Self { host, port, path })
+    }
+
+    /// Host name without port, and brackets of IPv6 address.
+    fn hostname(&self) -> &'a str {
+        let host = match self.host.rsplit_once(':') {
+            Some((host, _)) if !self.host.ends_with(']') => host,
+            _ => self.host,
+        };
+        host.trim_start_matches('[').trim_end_matches(']')
+    }
+}
@@ -0,0 +1,221 @@
+//! [tokio_util::codec] for websocket frames, Behind `tokio-util` feature.
+//!
+//! [WebSocketCodec] parses and encodes single frames, With the same masking and length rules as [Connection](crate::Connection).
+//! But it doesn't reassemble fragments, doesn't validate UTF-8 of text frames, And doesn't reply to `Ping` or `Close` frames.
+//! So it fits into existing `Framed` pipelines (For example: a proxy), Otherwise use [WebSocket](crate::WebSocket).
+//!
+//! ### Example
+//!
+//! ```no_run
+//! use futures::{SinkExt, StreamExt};
+//! use tokio_util::codec::Framed;
+//! use web_socket::codec::{Frame, WebSocketCodec};
+//!
+//! # async fn run(stream: tokio::net::TcpStream) -> std::io::Result<()> {
+//! let mut framed = Framed::new(stream, WebSocketCodec::server());
+//! while let Some(frame) = framed.next().await {
+//!     let frame = frame?;
+//!     match frame.opcode {
+//!         // Close
+//!         8 => return framed.send(frame).await,
+//!         // Ping
+//!         9 => framed.send(Frame::new(10, frame.data)).await?,
+//!         _ => framed.send(frame).await?,
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    connection::encode_head,
+    frame::{check_head, decode_extended_len},
+    mask, ProtocolError, Role,
+};
+use bytes::{Bytes, BytesMut};
+use std::io::{Error, Result};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// A websocket frame, That is decoded or encoded by [WebSocketCodec].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// Indicates that this is the final fragment in a message.
+    pub fin: bool,
+    /// `0` (continuation), `1` (text), `2` (binary), `8` (close), `9` (ping) or `10` (pong).
+    pub opcode: u8,
+    /// Payload of the frame, It is never masked.
+    pub data: Bytes,
+}
+
+impl Frame {
+    /// Create a final (not fragmented) frame.
+    #[inline]
+    pub fn new(opcode: u8, data: impl Into<Bytes>) -> Self {
+        Self {
+            fin: true,
+            opcode,
+            data: data.into(),
+        }
+    }
+}
+
+impl From<&str> for Frame {
+    #[inline]
+    fn from(text: &str) -> Self {
+        Self::new(1, Bytes::copy_from_slice(text.as_bytes()))
+    }
+}
+
+impl From<&[u8]> for Frame {
+    #[inline]
+    fn from(data: &[u8]) -> Self {
+        Self::new(2, Bytes::copy_from_slice(data))
+    }
+}
+
+/// Decodes frames that are received by `role`, And encodes frames that are sent by `role`.
+///
+/// - Frames sent by the client are masked with a random masking-key, Frames received by the server must be masked.
+/// - Reserved bits must be `0`, Because extensions are not supported.
+/// - Continuation frames must follow a started message, And control frames must not be fragmented.
+#[derive(Debug, Clone)]
+pub struct WebSocketCodec {
+    /// Maximum payload length of a frame in bytes, See [Connection::max_payload_len](crate::Connection::max_payload_len).
+    ///
+    /// Default: 16 MiB
+    pub max_payload_len: usize,
+
+    /// Whether to reject payload lengths, That are not encoded in the minimal number of bytes.
+    ///
+    /// Default: `false`
+    pub strict_length_encoding: bool,
+
+    role: Role,
+    /// A fragmented message is started, And not yet finished.
+    fragmented: bool,
+}
+
+impl WebSocketCodec {
+    /// Create a codec for the `role`.
+    pub fn new(role: Role) -> Self {
+        Self {
+            max_payload_len: 16 * 1024 * 1024,
+            strict_length_encoding: false,
+            role,
+            fragmented: false,
+        }
+    }
+
+    /// Create a codec for websocket client.
+    #[inline]
+    pub fn client() -> Self {
+        Self::new(Role::Client)
+    }
+
+    /// Create a codec for websocket server.
+    #[inline]
+    pub fn server() -> Self {
+        Self::new(Role::Server)
+    }
+
+    /// Returns the role of this codec.
+    #[inline]
+    pub fn role(&self) -> Role {
+        self.role
+    }
+}
+
+impl Decoder for WebSocketCodec {
+    type Item = Frame;
+    type Error = Error;
+
+    /// Payload is not reserved upfront, So that a peer can't make us allocate memory, Just by sending a frame header.
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Frame>> {
+        let [b1, b2, ..] = src[..] else {
+            return Ok(None);
+        };
+        check_head(self.role, [b1, b2], 0)?;
+        let fin = b1 & 0x80 != 0;
+        let opcode = b1 & 0x0f;
+        match opcode {
+            0 if self.fragmented => {}
+            1 | 2 if !self.fragmented => {}
+            8..=10 => {}
+            // 3-7 and 11-15 are reserved for further non-control and control frames
+            3..=7 | 11.. => return Err(ProtocolError::UnknownOpcode.into()),
+            _ => return Err(ProtocolError::InvalidContinuation.into()),
+        }
+
+        let offset = match b2 & 0x7f {
+            126 => 4,
+            127 => 10,
+            _ => 2,
+        };
+        let head_len = match self.role {
+            Role::Server => offset + 4,
+            Role::Client => offset,
+        };
+        if src.len() < head_len {
+            return Ok(None);
+        }
+        let len = match offset {
+            2 => (b2 & 0x7f) as usize,
+            _ => decode_extended_len(&src[2..offset], self.strict_length_encoding)?,
+        };
+        if len > self.max_payload_len {
+            return Err(ProtocolError::PayloadTooLarge.into());
+        }
+        if src.len() - head_len < len {
+            return Ok(None);
+        }
+
+        let head = src.split_to(head_len);
+        let mut data = src.split_to(len);
+        if let Role::Server = self.role {
+            let key = head[offset..].try_into().unwrap();
+            mask::apply_mask(&mut data, key);
+        }
+        if opcode < 8 {
+            self.fragmented = !fin;
+        }
+        Ok(Some(Frame {
+            fin,
+            opcode,
+            data: data.freeze(),
+        }))
+    }
+}
+
+impl Encoder<Frame> for WebSocketCodec {
+    type Error = Error;
+
+    /// Frames are checked by the same rules as received frames, So that the remote endpoint doesn't fail the connection:
+    /// Opcode must be defined, And control frames must not be fragmented or have a payload of more than 125 bytes.
+    fn encode(&mut self, frame: Frame, dst: &mut BytesMut) -> Result<()> {
+        match frame.opcode {
+            0..=2 => {}
+            8..=10 if !frame.fin => return Err(ProtocolError::FragmentedControlFrame.into()),
+            8..=10 if frame.data.len() > 125 => {
+                return Err(ProtocolError::ControlFrameTooLarge.into())
+            }
+            8..=10 => {}
+            _ => return Err(ProtocolError::UnknownOpcode.into()),
+        }
+        let frame = crate::Frame {
+            fin: frame.fin,
+            opcode: frame.opcode,
+            data: &frame.data,
+        };
+        let mut head = [0; 14];
+        let head_len = encode_head(self.role, &frame, &mut head);
+        dst.reserve(head_len + frame.data.len());
+        dst.extend_from_slice(&head[..head_len]);
+        let offset = dst.len();
+        dst.extend_from_slice(frame.data);
+        if let Role::Client = self.role {
+            let key = head[head_len - 4..head_len].try_into().unwrap();
+            mask::apply_mask(&mut dst[offset..], key);
+        }
+        Ok(())
+    }
+}
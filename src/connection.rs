@@ -0,0 +1,960 @@
+#![allow(clippy::unusual_byte_groupings)]
+use crate::io::{Error, ErrorKind, Result};
+use crate::{
+    budget::Reservation,
+    extension::{rsv_bits, DataFrame, Extension},
+    frame::{check_head, decode_extended_len},
+    mask,
+    utf8::Utf8Validator,
+    *,
+};
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use core::{
+    mem::take,
+    sync::atomic::{AtomicU16, AtomicU8, Ordering},
+    task::{ready, Poll},
+};
+#[cfg(not(feature = "std"))]
+use spin::{Mutex, MutexGuard};
+#[cfg(feature = "std")]
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Protocol state of a websocket connection, That doesn't perform any I/O (sans-IO).
+///
+/// - Received bytes are passed to [Connection::receive] (Or read into [Connection::read_buf]), And parsed by [Connection::next_event].
+/// - Frames are encoded into the outgoing bytes (See [Connection::outgoing]), That are written by the caller.
+///
+/// So the protocol can be driven by any I/O model, For example: `mio` event loop, `io_uring` runtime, Blocking I/O or a fuzzer.
+/// [WebSocket] is a wrapper over it, That drives it with `tokio` streams.
+///
+/// ### Example
+///
+/// ```rust
+/// use web_socket::{Connection, Event, Role};
+///
+/// let mut client = Connection::new(Role::Client);
+/// let mut server = Connection::new(Role::Server);
+///
+/// client.encode("Hello")?;
+/// server.receive(client.outgoing());
+/// client.consume_outgoing(client.outgoing().len());
+///
+/// assert!(matches!(server.next_event()?, Some(Event::Data { data, .. }) if &*data == b"Hello"));
+/// // More bytes are needed.
+/// assert!(server.next_event()?.is_none());
+/// # Ok::<_, web_socket::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Connection {
+    /// Maximum allowed payload length in bytes.
+    ///
+    /// Default: 16 MB
+    pub max_payload_len: usize,
+
+    /// Maximum allowed message length in bytes, Across all fragments of a message.
+    ///
+    /// Default: 64 MB
+    pub max_message_len: usize,
+
+    /// Memory budget for partially received frames and messages, It is unlimited if `None`.
+    ///
+    /// See [MemoryBudget] for more details.
+    ///
+    /// Default: `None`
+    pub memory_budget: Option<Arc<MemoryBudget>>,
+
+    /// Whether a [ProtocolError] fails the connection with a `Close` frame of the corresponding status code. (See [ProtocolError::close_code])
+    ///
+    /// The `Close` frame is written by `recv`, Before [Event::Error] is returned. (Or by the next write, See [Connection::encode_close])
+    /// Otherwise the connection is closed, Without sending a `Close` frame.
+    ///
+    /// Default: `true`
+    pub close_on_error: bool,
+
+    /// Whether to reject payload lengths, That are not encoded in the minimal number of bytes.
+    /// For example: length of `5` bytes, That is encoded as 16-bit extended payload length.
+    ///
+    /// Default: `false`
+    pub strict_length_encoding: bool,
+
+    /// Size of the internal read buffer in bytes, (See [Connection::read_buf])
+    ///
+    /// As many frames as are already buffered are parsed, before reading from the underlying stream.
+    /// So wrapping the stream with `BufReader` is unnecessary. Set to `0` to read directly from the stream.
+    ///
+    /// Default: 8 KB
+    pub read_buffer_size: usize,
+
+    role: Role,
+    fragment: Option<MessageType>,
+    /// Length of the message (fragments) that is received so far.
+    message_len: usize,
+    /// Fragments of the message, That is being reassembled by [Connection::next_message].
+    message: Vec<u8>,
+    message_reserved: Reservation,
+    utf8: Utf8Validator,
+    incoming: Incoming,
+    read: ReadState,
+    /// Whether [Connection::read_buf] returned the payload buffer.
+    read_into_payload: bool,
+    /// Encoded frames that are not yet written, `outgoing[written..]`
+    outgoing: Vec<u8>,
+    written: usize,
+    protocol: Option<String>,
+    /// Whether any extension is negotiated, So that `shared.extensions` is locked only when needed.
+    has_extensions: bool,
+    /// Reserved bits that are used by negotiated extensions.
+    rsv_bits: u8,
+    /// Decoding state of the extensions, That is split for the read half. (See [Extension::split_decoder])
+    ///
+    /// It is empty, If the connection isn't split. `None` means the extension is shared with the write half.
+    decoders: Vec<Option<Box<dyn Extension>>>,
+    shared: Arc<Shared>,
+}
+
+/// State that is shared between [WsReader] and [WsWriter] halves.
+#[derive(Debug, Default)]
+struct Shared {
+    /// [CloseState] as `u8`
+    state: AtomicU8,
+    /// Status code of the received `Close` frame, That is echoed back.
+    close_code: AtomicU16,
+    extensions: Mutex<Vec<Box<dyn Extension>>>,
+}
+
+/// Received bytes that are not yet parsed, `buf[start..end]`.
+///
+/// Bytes after `end` are initialized, So they are handed out by [Connection::read_buf] without zeroing them again.
+#[derive(Debug, Default)]
+struct Incoming {
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+}
+
+impl Incoming {
+    fn data(&self) -> &[u8] {
+        &self.buf[self.start..self.end]
+    }
+
+    fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Copy the received bytes into `buf`, Returns the number of bytes copied.
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let amt = buf.len().min(self.end - self.start);
+        buf[..amt].copy_from_slice(&self.buf[self.start..self.start + amt]);
+        self.start += amt;
+        amt
+    }
+
+    /// Returns `len` bytes after the received bytes.
+    fn spare(&mut self, len: usize) -> &mut [u8] {
+        if self.is_empty() {
+            self.start = 0;
+            self.end = 0;
+        } else if self.buf.len() - self.end < len {
+            self.buf.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+        }
+        if self.buf.len() < self.end + len {
+            self.buf.resize(self.end + len, 0);
+        }
+        &mut self.buf[self.end..self.end + len]
+    }
+
+    fn extend(&mut self, data: &[u8]) {
+        self.spare(data.len()).copy_from_slice(data);
+        self.end += data.len();
+    }
+}
+
+macro_rules! err { [$err: expr] => { return Poll::Ready(Ok(Event::Error($err))) }; }
+
+/// Initial capacity of the payload buffer, It is doubled as the bytes arrive.
+const INITIAL_PAYLOAD_CAPACITY: usize = 4096;
+
+/// Progress of a partially received frame.
+///
+/// It is kept in [Connection], So that parsing resumes exactly where it stopped, Once more bytes are received.
+#[derive(Debug, Default)]
+struct ReadState {
+    /// Frame header: 2 bytes, Extended payload length (0, 2 or 8 bytes) and Masking-key (0 or 4 bytes).
+    head: [u8; 14],
+    /// Number of header bytes that are read.
+    head_len: usize,
+    /// Payload buffer, It grows as the bytes arrive. `payload[filled..]` is not yet received.
+    payload: Vec<u8>,
+    filled: usize,
+    /// Memory that is acquired for the payload buffer.
+    reserved: Reservation,
+    /// Number of bytes that are needed, To make progress.
+    need: usize,
+}
+
+impl Connection {
+    /// Create the protocol state of a websocket connection, With default configuration.
+    pub fn new(role: Role) -> Self {
+        Self {
+            max_payload_len: 16 * 1024 * 1024,
+            max_message_len: 64 * 1024 * 1024,
+            memory_budget: None,
+            close_on_error: true,
+            strict_length_encoding: false,
+            read_buffer_size: 8 * 1024,
+            role,
+            fragment: None,
+            message_len: 0,
+            message: Vec::new(),
+            message_reserved: Reservation::default(),
+            utf8: Utf8Validator::default(),
+            incoming: Incoming::default(),
+            read: ReadState::default(),
+            read_into_payload: false,
+            outgoing: Vec::new(),
+            written: 0,
+            protocol: None,
+            has_extensions: false,
+            rsv_bits: 0,
+            decoders: Vec::new(),
+            shared: Arc::default(),
+        }
+    }
+
+    /// Apply handshake parameters, that were agreed by both endpoints.
+    pub fn with_negotiated(mut self, negotiated: handshake::Negotiated) -> Self {
+        self.protocol = negotiated.protocol;
+        self.has_extensions = !negotiated.extensions.is_empty();
+        self.rsv_bits = rsv_bits(&negotiated.extensions);
+        *self.extensions_mut() = negotiated.extensions;
+        self
+    }
+
+    /// Returns the role of this endpoint.
+    #[inline]
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// Returns the subprotocol that was selected during the handshake, if any.
+    #[inline]
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    /// Returns the extensions that were negotiated during the handshake, in the order they are applied.
+    ///
+    /// Extensions are shared between [WsReader] and [WsWriter] halves, So they are locked until the guard is dropped.
+    #[inline]
+    pub fn extensions(&self) -> MutexGuard<'_, Vec<Box<dyn Extension>>> {
+        self.extensions_mut()
+    }
+
+    pub(crate) fn extensions_mut(&self) -> MutexGuard<'_, Vec<Box<dyn Extension>>> {
+        lock(&self.shared.extensions)
+    }
+
+    /// Returns `true`, If any extension is negotiated.
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub(crate) fn has_extensions(&self) -> bool {
+        self.has_extensions
+    }
+
+    /// Returns the state of the closing handshake.
+    ///
+    /// The state is shared between [WsReader] and [WsWriter] halves.
+    #[inline]
+    pub fn close_state(&self) -> CloseState {
+        CloseState::from(self.shared.state.load(Ordering::Acquire))
+    }
+
+    /// Returns `true`, Once the closing handshake has started (by either endpoint), Or the connection failed.
+    ///
+    /// No more data can be sent, once the websocket is closed.
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        self.close_state() != CloseState::Open
+    }
+
+    /// Atomically change the close state, Returns `false` if the current state isn't `from`.
+    fn transition(&self, from: CloseState, to: CloseState) -> bool {
+        self.shared
+            .state
+            .compare_exchange(from as u8, to as u8, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    fn set_closed(&self) {
+        self.shared
+            .state
+            .store(CloseState::Closed as u8, Ordering::Release);
+    }
+
+    /// Split the state into read and write halves, That share the close state and extensions.
+    ///
+    /// Decoding state of the extensions is owned by the read half, If they can split it. (See [Extension::split_decoder])
+    #[cfg(feature = "tokio")]
+    pub(crate) fn split(mut self) -> (Self, Self) {
+        if self.decoders.is_empty() {
+            let mut extensions = lock(&self.shared.extensions);
+            self.decoders = extensions
+                .iter_mut()
+                .map(|ext| ext.split_decoder())
+                .collect();
+        }
+        let writer = Self {
+            max_payload_len: self.max_payload_len,
+            max_message_len: self.max_message_len,
+            memory_budget: self.memory_budget.clone(),
+            close_on_error: self.close_on_error,
+            strict_length_encoding: self.strict_length_encoding,
+            read_buffer_size: self.read_buffer_size,
+            outgoing: take(&mut self.outgoing),
+            written: take(&mut self.written),
+            protocol: self.protocol.clone(),
+            has_extensions: self.has_extensions,
+            rsv_bits: self.rsv_bits,
+            shared: self.shared.clone(),
+            ..Self::new(self.role)
+        };
+        (self, writer)
+    }
+
+    /// Returns `true`, If both halves are split from the same connection.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn is_pair_of(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+
+    /// Join the halves, That were created by [Connection::split].
+    #[cfg(feature = "tokio")]
+    pub(crate) fn unsplit(self, writer: Self) -> Self {
+        Self {
+            outgoing: writer.outgoing,
+            written: writer.written,
+            ..self
+        }
+    }
+
+    // ---------------------------------------------------------------------- Read
+
+    /// Pass the received bytes to the connection, They are parsed by [Connection::next_event].
+    pub fn receive(&mut self, data: &[u8]) {
+        self.incoming.extend(data);
+    }
+
+    /// Returns the buffered bytes, That are received but not yet parsed.
+    pub fn buffered(&self) -> &[u8] {
+        self.incoming.data()
+    }
+
+    /// Returns the buffer, Where the next received bytes should be read into. Followed by [Connection::advance].
+    ///
+    /// It is an alternative to [Connection::receive], That avoids an extra copy.
+    /// Up to [Connection::read_buffer_size] bytes are read at once, But large payloads are read directly into the payload buffer.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// use std::io::Read;
+    /// use web_socket::{Connection, Role};
+    ///
+    /// # #[cfg(feature = "std")]
+    /// # fn run(mut stream: std::net::TcpStream) -> std::io::Result<()> {
+    /// let mut conn = Connection::new(Role::Server);
+    /// loop {
+    ///     while let Some(event) = conn.next_event()? {
+    ///         println!("{event:?}");
+    ///     }
+    ///     let amt = stream.read(conn.read_buf())?;
+    ///     conn.advance(amt);
+    /// }
+    /// # }
+    /// ```
+    pub fn read_buf(&mut self) -> &mut [u8] {
+        let read = &mut self.read;
+        // Large payloads bypass the buffer, To avoid an extra copy.
+        self.read_into_payload = self.incoming.is_empty()
+            && read.filled < read.payload.len()
+            && read.need >= self.read_buffer_size;
+
+        if self.read_into_payload {
+            return &mut read.payload[read.filled..];
+        }
+        let len = match self.read_buffer_size {
+            0 => read.need.max(1),
+            len => len,
+        };
+        self.incoming.spare(len)
+    }
+
+    /// Mark `amt` bytes of [Connection::read_buf] as received.
+    ///
+    /// ### Panics
+    ///
+    /// If `amt` is larger than the buffer that was returned by [Connection::read_buf].
+    pub fn advance(&mut self, amt: usize) {
+        if take(&mut self.read_into_payload) {
+            let read = &mut self.read;
+            assert!(
+                read.filled + amt <= read.payload.len(),
+                "`amt` is out of bounds"
+            );
+            read.filled += amt;
+        } else {
+            let incoming = &mut self.incoming;
+            assert!(
+                incoming.end + amt <= incoming.buf.len(),
+                "`amt` is out of bounds"
+            );
+            incoming.end += amt;
+        }
+    }
+
+    /// Returns the buffered bytes, Along with the bytes of a partially received frame.
+    #[cfg(any(feature = "std", feature = "embedded-io-async"))]
+    pub(crate) fn into_buffered(self) -> Vec<u8> {
+        let read = self.read;
+        let mut buffered = read.head[..read.head_len].to_vec();
+        buffered.extend_from_slice(&read.payload[..read.filled]);
+        buffered.extend_from_slice(self.incoming.data());
+        buffered
+    }
+
+    /// Returns an error, If the closing handshake was started by the remote endpoint, Or the connection failed.
+    #[cfg(any(feature = "std", feature = "embedded-io-async"))]
+    pub(crate) fn ensure_readable(&self) -> Result<()> {
+        if let CloseState::CloseReceived | CloseState::Closed = self.close_state() {
+            return Err(Error::new(ErrorKind::NotConnected, "read after close"));
+        }
+        Ok(())
+    }
+
+    /// The underlying stream failed, Partially received frame and message are discarded.
+    pub(crate) fn fail(&mut self) {
+        self.read = ReadState::default();
+        self.message_len = 0;
+        self.message = Vec::new();
+        self.message_reserved.clear();
+        self.set_closed();
+    }
+
+    /// Parse the next [Event] from the received bytes, Returns `None` if more bytes are needed.
+    ///
+    /// Partially received frame is kept in the connection, So parsing resumes once more bytes are received.
+    pub fn next_event(&mut self) -> Result<Option<Event>> {
+        let event = match self.poll_frame() {
+            Poll::Ready(event) => event,
+            Poll::Pending => return Ok(None),
+        };
+        match event {
+            Ok(Event::Close { code, .. }) => self.on_close_received(code),
+            Ok(Event::Error(err)) => self.on_error(err),
+            Err(..) => self.fail(),
+            _ => {}
+        }
+        event.map(Some)
+    }
+
+    /// Parse the next [Event] from the received bytes, Where fragments are reassembled into a single message.
+    ///
+    /// Returned event is never a fragment, It is one of:
+    ///
+    /// - [Event::Data] of [DataType::Complete], Its length is limited by [Connection::max_message_len].
+    /// - [Event::Ping] or [Event::Pong], Control frames that are interleaved with fragments are returned as they arrive.
+    /// - [Event::Close], Or [Event::Error] (For example: [ProtocolError::MessageTooLarge]).
+    pub fn next_message(&mut self) -> Result<Option<Event>> {
+        loop {
+            let Some(event) = self.next_event()? else {
+                return Ok(None);
+            };
+            match event {
+                Event::Data {
+                    ty: DataType::Stream(stream),
+                    data,
+                } => {
+                    if !self.message_reserved.grow(&self.memory_budget, data.len()) {
+                        self.message = Vec::new();
+                        self.message_reserved.clear();
+                        self.on_error(ProtocolError::MessageTooLarge);
+                        return Ok(Some(Event::Error(ProtocolError::MessageTooLarge)));
+                    }
+                    match self.message.is_empty() {
+                        true => self.message = data.into_vec(),
+                        false => self.message.extend_from_slice(&data),
+                    }
+                    if let Stream::End(ty) = stream {
+                        self.message_reserved.clear();
+                        return Ok(Some(Event::Data {
+                            ty: DataType::Complete(ty),
+                            data: take(&mut self.message).into_boxed_slice(),
+                        }));
+                    }
+                }
+                Event::Error(_) => {
+                    self.message = Vec::new();
+                    self.message_reserved.clear();
+                    return Ok(Some(event));
+                }
+                event => return Ok(Some(event)),
+            }
+        }
+    }
+
+    fn on_error(&mut self, err: ProtocolError) {
+        self.read = ReadState::default();
+        self.message_len = 0;
+        self.utf8.reset();
+        match self.close_on_error {
+            // Fail the connection, With `Close` frame of the corresponding status code.
+            true => self.on_close_received(err.close_code().into()),
+            false => self.set_closed(),
+        }
+    }
+
+    fn on_close_received(&self, code: u16) {
+        // Reply to our `Close` frame, The closing handshake is complete.
+        if !self.transition(CloseState::CloseSent, CloseState::Closed) {
+            self.shared.close_code.store(code, Ordering::Release);
+            self.transition(CloseState::Open, CloseState::CloseReceived);
+        }
+    }
+
+    /// Decode a data frame with negotiated extensions, In reverse order.
+    ///
+    /// Shared extensions are locked, Only if the decoding state isn't split. (See [Connection::split])
+    fn decode_extensions(&mut self, frame: &mut DataFrame) -> Result<()> {
+        if self.decoders.is_empty() {
+            for ext in self.extensions_mut().iter_mut().rev() {
+                ext.decode(frame)?;
+            }
+            return Ok(());
+        }
+        for (i, decoder) in self.decoders.iter_mut().enumerate().rev() {
+            match decoder {
+                Some(decoder) => decoder.decode(frame)?,
+                None => lock(&self.shared.extensions)[i].decode(frame)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy the received bytes into the header, Until it is `len` bytes long.
+    fn fill_head(&mut self, len: usize) -> Poll<()> {
+        let read = &mut self.read;
+        if read.head_len < len {
+            read.head_len += self.incoming.read(&mut read.head[read.head_len..len]);
+            if read.head_len < len {
+                read.need = len - read.head_len;
+                return Poll::Pending;
+            }
+        }
+        Poll::Ready(())
+    }
+
+    // ### WebSocket Frame Header
+    //
+    // ```txt
+    //  0                   1                   2                   3
+    //  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+    // +-+-+-+-+-------+-+-------------+-------------------------------+
+    // |F|R|R|R| opcode|M| Payload len |    Extended payload length    |
+    // |I|S|S|S|  (4)  |A|     (7)     |             (16/64)           |
+    // |N|V|V|V|       |S|             |   (if payload len==126/127)   |
+    // | |1|2|3|       |K|             |                               |
+    // +-+-+-+-+-------+-+-------------+ - - - - - - - - - - - - - - - +
+    // |     Extended payload length continued, if payload len == 127  |
+    // + - - - - - - - - - - - - - - - +-------------------------------+
+    // |                               |Masking-key, if MASK set to 1  |
+    // +-------------------------------+-------------------------------+
+    // | Masking-key (continued)       |          Payload Data         |
+    // +-------------------------------- - - - - - - - - - - - - - - - +
+    // :                     Payload Data continued ...                :
+    // + - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - +
+    // |                     Payload Data continued ...                |
+    // +---------------------------------------------------------------+
+    // ```
+    /// Parse a frame from the received bytes, `Poll::Pending` means that more bytes are needed.
+    fn poll_frame(&mut self) -> Poll<Result<Event>> {
+        ready!(self.fill_head(2));
+        let [b1, b2] = [self.read.head[0], self.read.head[1]];
+
+        if let Err(err) = check_head(self.role, [b1, b2], self.rsv_bits) {
+            err!(err);
+        }
+        let fin = b1 & 0b_1000_0000 != 0;
+        let rsv = b1 & 0b_111_0000;
+        let opcode = b1 & 0b_1111;
+        let len = (b2 & 0b_111_1111) as usize;
+        let is_masked = b2 & 0b_1000_0000 != 0;
+
+        // 3-7 are reserved for further non-control frames.
+        if opcode >= 8 {
+            let Some(msg) = ready!(self.poll_payload(2, len, is_masked)) else {
+                err!(ProtocolError::MessageTooLarge);
+            };
+            match opcode {
+                8 => Poll::Ready(Ok(on_close(&msg))),
+                9 => Poll::Ready(Ok(Event::Ping(msg))),
+                10 => Poll::Ready(Ok(Event::Pong(msg))),
+                // 11-15 are reserved for further control frames
+                _ => err!(ProtocolError::UnknownOpcode),
+            }
+        } else {
+            let ty = match (opcode, fin, self.fragment) {
+                (2, true, None) => DataType::Complete(MessageType::Binary),
+                (1, true, None) => DataType::Complete(MessageType::Text),
+                (2, false, None) => DataType::Stream(Stream::Start(MessageType::Binary)),
+                (1, false, None) => DataType::Stream(Stream::Start(MessageType::Text)),
+                (0, false, Some(ty)) => DataType::Stream(Stream::Next(ty)),
+                (0, true, Some(ty)) => DataType::Stream(Stream::End(ty)),
+                // 3-7 are reserved for further non-control frames
+                (3..=7, ..) => err!(ProtocolError::UnknownOpcode),
+                _ => err!(ProtocolError::InvalidContinuation),
+            };
+            let (offset, len) = match len {
+                126 | 127 => {
+                    let offset = if len == 126 { 4 } else { 10 };
+                    ready!(self.fill_head(offset));
+                    let head = &self.read.head[2..offset];
+                    match decode_extended_len(head, self.strict_length_encoding) {
+                        Ok(len) => (offset, len),
+                        Err(err) => err!(err),
+                    }
+                }
+                len => (2, len),
+            };
+            if len > self.max_payload_len {
+                err!(ProtocolError::PayloadTooLarge);
+            }
+            if self.message_len.saturating_add(len) > self.max_message_len {
+                err!(ProtocolError::MessageTooLarge);
+            }
+            let Some(mut data) = ready!(self.poll_payload(offset, len, is_masked)) else {
+                err!(ProtocolError::MessageTooLarge);
+            };
+            // The frame is complete, So the fragmentation state can be updated.
+            self.fragment = match ty {
+                DataType::Stream(Stream::Start(ty) | Stream::Next(ty)) => Some(ty),
+                _ => None,
+            };
+            if self.has_extensions {
+                let mut frame = DataFrame {
+                    fin,
+                    rsv,
+                    opcode,
+                    data: data.into(),
+                };
+                if let Err(err) = self.decode_extensions(&mut frame) {
+                    match ProtocolError::from_io(&err) {
+                        Some(err) => err!(err),
+                        None => return Poll::Ready(Err(err)),
+                    }
+                }
+                data = frame.data.into_boxed_slice();
+            }
+            // Payload length may change by extensions, For example: decompression
+            let message_len = self.message_len + data.len();
+            if message_len > self.max_message_len {
+                err!(ProtocolError::MessageTooLarge);
+            }
+            self.message_len = if fin { 0 } else { message_len };
+            if let MessageType::Text = ty.ty() {
+                if let Err(err) = self.utf8.validate(&data, fin) {
+                    err!(err);
+                }
+            }
+            Poll::Ready(Ok(Event::Data { ty, data }))
+        }
+    }
+
+    /// Read Masking-key (if any) after `offset` bytes of the header, And the payload of `len` bytes.
+    ///
+    /// Read state is reset once the payload is complete.
+    /// Returns `None`, If the payload exceeds the memory budget.
+    fn poll_payload(
+        &mut self,
+        offset: usize,
+        len: usize,
+        is_masked: bool,
+    ) -> Poll<Option<Box<[u8]>>> {
+        let head_len = offset + if is_masked { 4 } else { 0 };
+        ready!(self.fill_head(head_len));
+
+        let read = &mut self.read;
+        while read.filled < len {
+            if read.filled == read.payload.len() {
+                // Grow the buffer as the bytes arrive, Instead of allocating `len` bytes upfront.
+                // So that a peer can't make us allocate memory, Just by sending a frame header.
+                let amt = (len - read.filled).min(read.filled.max(INITIAL_PAYLOAD_CAPACITY));
+                if !read.reserved.grow(&self.memory_budget, amt) {
+                    return Poll::Ready(None);
+                }
+                read.payload.reserve_exact(amt);
+                read.payload.resize(read.filled + amt, 0);
+            }
+            if self.incoming.is_empty() {
+                read.need = len - read.filled;
+                return Poll::Pending;
+            }
+            read.filled += self.incoming.read(&mut read.payload[read.filled..]);
+        }
+
+        let read = take(&mut self.read);
+        let mut data = read.payload.into_boxed_slice();
+        if is_masked {
+            let key = read.head[offset..head_len].try_into().unwrap();
+            mask::apply_mask(&mut data, key);
+        }
+        Poll::Ready(Some(data))
+    }
+
+    // ---------------------------------------------------------------------- Write
+
+    /// Returns the encoded frames, That are not yet written.
+    pub fn outgoing(&self) -> &[u8] {
+        &self.outgoing[self.written..]
+    }
+
+    /// Mark `amt` bytes of [Connection::outgoing] as written.
+    pub fn consume_outgoing(&mut self, amt: usize) {
+        self.written += amt;
+        assert!(
+            self.written <= self.outgoing.len(),
+            "`amt` is out of bounds"
+        );
+        if self.written == self.outgoing.len() {
+            self.outgoing.clear();
+            self.written = 0;
+        }
+    }
+
+    /// Returns `true`, If the outgoing frames are partially written.
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub(crate) fn is_partially_written(&self) -> bool {
+        self.written > 0
+    }
+
+    /// Returns the outgoing buffer, Where the frames are encoded.
+    #[cfg(feature = "tokio")]
+    #[inline]
+    pub(crate) fn queue(&mut self) -> &mut Vec<u8> {
+        &mut self.outgoing
+    }
+
+    /// Encode a `Ping` or `Pong` frame ahead of the queued frames, Unless they are partially written.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn encode_ahead(&mut self, opcode: u8, data: &[u8]) -> Result<()> {
+        self.ensure_open()?;
+        let queued = self.outgoing.len();
+        let frame = Frame {
+            fin: true,
+            opcode,
+            data,
+        };
+        encode_frame(self.role, frame, &mut self.outgoing);
+        if !self.is_partially_written() {
+            self.outgoing.rotate_left(queued);
+        }
+        Ok(())
+    }
+
+    /// Returns an error, If the websocket is closed. The reply to the received `Close` frame (if any) is encoded.
+    pub(crate) fn ensure_open(&mut self) -> Result<()> {
+        if self.is_closed() {
+            // The remote endpoint is waiting for our `Close` frame.
+            self.echo_close();
+            return Err(Error::new(ErrorKind::NotConnected, "send after close"));
+        }
+        Ok(())
+    }
+
+    /// Encode a message into the outgoing bytes, (See [WebSocket::send])
+    ///
+    /// Returns an error, If the websocket is closed.
+    pub fn encode<'a>(&mut self, data: impl Into<Frame<'a>>) -> Result<()> {
+        self.ensure_open()?;
+        let frame = data.into();
+        if frame.opcode >= 8 || !self.has_extensions {
+            encode_frame(self.role, frame, &mut self.outgoing);
+            return Ok(());
+        }
+        let frame = self.encode_extensions(frame)?;
+        encode_frame(self.role, Frame::from(&frame), &mut self.outgoing);
+        Ok(())
+    }
+
+    /// Encode a `Ping` frame into the outgoing bytes, (See [WebSocket::send_ping])
+    pub fn encode_ping(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+        self.encode(Frame {
+            fin: true,
+            opcode: 9,
+            data: data.as_ref(),
+        })
+    }
+
+    /// Encode a `Pong` frame into the outgoing bytes, (See [WebSocket::send_pong])
+    pub fn encode_pong(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+        self.encode(Frame {
+            fin: true,
+            opcode: 10,
+            data: data.as_ref(),
+        })
+    }
+
+    /// Encode a `Close` frame into the outgoing bytes, (See [WebSocket::send_close])
+    ///
+    /// - If a `Close` frame was already received, It is a reply, And an empty `reason` echoes the received status code.
+    /// - Does nothing, If a `Close` frame was already sent.
+    pub fn encode_close<T>(&mut self, reason: T)
+    where
+        T: CloseReason,
+        T::Bytes: AsRef<[u8]>,
+    {
+        let reason = reason.to_bytes();
+        let reason = reason.as_ref();
+        if self.transition(CloseState::Open, CloseState::CloseSent) {
+            self.encode_close_frame(reason);
+        } else if reason.is_empty() {
+            self.echo_close();
+        } else if self.transition(CloseState::CloseReceived, CloseState::Closed) {
+            self.encode_close_frame(reason);
+        }
+    }
+
+    /// Reply to the received `Close` frame (if any) with the same status code.
+    pub(crate) fn echo_close(&mut self) {
+        if self.transition(CloseState::CloseReceived, CloseState::Closed) {
+            let code = self.shared.close_code.load(Ordering::Acquire);
+            self.encode_close_frame(&code.to_be_bytes());
+        }
+    }
+
+    fn encode_close_frame(&mut self, reason: &[u8]) {
+        let frame = Frame {
+            fin: true,
+            opcode: 8,
+            data: reason,
+        };
+        encode_frame(self.role, frame, &mut self.outgoing);
+    }
+
+    /// Encode a frame, That is encoded ahead of time. (See [WebSocket::send_prepared])
+    pub fn encode_prepared(&mut self, prepared: &PreparedFrame) -> Result<()> {
+        self.ensure_open()?;
+        match self.prepared(prepared) {
+            Some((_, bytes)) if self.role == Role::Server => self.outgoing.extend_from_slice(bytes),
+            Some((frame, _)) => encode_frame(self.role, frame, &mut self.outgoing),
+            None => return self.encode(prepared.plain_parts().0),
+        }
+        Ok(())
+    }
+
+    /// Returns the frame of `prepared` (and its encoded bytes), That is sent as is.
+    ///
+    /// Returns `None`, If the frame has to be encoded by the negotiated extensions.
+    pub(crate) fn prepared<'a>(
+        &mut self,
+        prepared: &'a PreparedFrame,
+    ) -> Option<(Frame<'a>, &'a [u8])> {
+        let plain = prepared.plain_parts();
+        if !self.has_extensions || plain.0.opcode >= 8 {
+            return Some(plain);
+        }
+        let (frame, bytes, params) = prepared.compressed_parts()?;
+        let accepted = match &mut self.extensions_mut()[..] {
+            [ext] => ext.accept_prepared(params),
+            _ => false,
+        };
+        accepted.then_some((frame, bytes))
+    }
+
+    /// Encode a data frame with negotiated extensions.
+    pub(crate) fn encode_extensions(&self, frame: Frame) -> Result<DataFrame> {
+        let mut frame = DataFrame {
+            fin: frame.fin,
+            rsv: 0,
+            opcode: frame.opcode,
+            data: frame.data.to_vec(),
+        };
+        for ext in self.extensions_mut().iter_mut() {
+            ext.encode(&mut frame)?;
+        }
+        Ok(frame)
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    #[cfg(feature = "std")]
+    return mutex.lock().unwrap_or_else(PoisonError::into_inner);
+    #[cfg(not(feature = "std"))]
+    mutex.lock()
+}
+
+/// Encode frame header into `head`, Along with a random masking-key for the client. Returns the header length.
+pub(crate) fn encode_head(role: Role, frame: &Frame, head: &mut [u8; 14]) -> usize {
+    match role {
+        Role::Server => frame.encode_header(None, head),
+        Role::Client => frame.encode_header(Some(random_bytes()), head),
+    }
+}
+
+/// Encode `frame` into `buf`, Client payload is masked.
+pub(crate) fn encode_frame(role: Role, frame: Frame<'_>, buf: &mut Vec<u8>) {
+    let mut head = [0; 14];
+    let head_len = encode_head(role, &frame, &mut head);
+    buf.extend_from_slice(&head[..head_len]);
+    let offset = buf.len();
+    buf.extend_from_slice(frame.data);
+    if let Role::Client = role {
+        let key = head[head_len - 4..head_len].try_into().unwrap();
+        mask::apply_mask(&mut buf[offset..], key);
+    }
+}
+
+/// - If there is a body, the first two bytes of the body MUST be a 2-byte unsigned integer (in network byte order: Big Endian)
+///   representing a status code with value /code/ defined in [Section 7.4](https:///datatracker.ietf.org/doc/html/rfc6455#section-7.4).
+///   Following the 2-byte integer,
+///
+/// - The application MUST NOT send any more data frames after sending a `Close` frame.
+///
+/// - If an endpoint receives a Close frame and did not previously send a
+///   Close frame, the endpoint MUST send a Close frame in response.  (When
+///   sending a Close frame in response, the endpoint typically echos the
+///   status code it received.)  It SHOULD do so as soon as practical.  An
+///   endpoint MAY delay sending a Close frame until its current message is
+///   sent
+///
+/// - After both sending and receiving a Close message, an endpoint
+///   considers the WebSocket connection closed and MUST close the
+///   underlying TCP connection.
+fn on_close(msg: &[u8]) -> Event {
+    if msg.len() == 1 {
+        return Event::Error(ProtocolError::InvalidCloseCode);
+    }
+    let code = msg
+        .get(..2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .unwrap_or(1000);
+
+    match code {
+        1000..=1003 | 1007..=1011 | 1015 | 3000..=3999 | 4000..=4999 => {
+            match msg.get(2..).map(|data| String::from_utf8(data.to_vec())) {
+                Some(Ok(msg)) => Event::Close {
+                    code,
+                    reason: msg.into_boxed_str(),
+                },
+                None => Event::Close {
+                    code,
+                    reason: "".into(),
+                },
+                Some(Err(_)) => Event::Error(ProtocolError::InvalidUtf8),
+            }
+        }
+        _ => Event::Error(ProtocolError::InvalidCloseCode),
+    }
+}
@@ -0,0 +1,456 @@
+//! # Per-Message Compression
+//!
+//! `permessage-deflate` extension, As defined in [RFC 7692](https://datatracker.ietf.org/doc/html/rfc7692)
+//!
+//! The payload of a message is compressed with [DEFLATE](https://datatracker.ietf.org/doc/html/rfc1951) algorithm,
+//! And the first frame of a compressed message is marked with `RSV1` bit.
+//!
+//! ### Example
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use web_socket::{client, deflate::PerMessageDeflate};
+//!
+//! # async fn run() -> Result<(), web_socket::handshake::Error> {
+//! let config = client::Config {
+//!     extensions: vec![Arc::new(PerMessageDeflate::default())],
+//!     ..Default::default()
+//! };
+//! let mut ws = client::connect_with("ws://example.com/chat", &config).await?;
+//! ws.send(r#"{"event":"subscribe","channel":"ticker"}"#).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    extension::{DataFrame, Extension, Negotiator, Offer, RSV1},
+    ProtocolError,
+};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use std::io::{Error, Result};
+
+const NAME: &str = "permessage-deflate";
+
+/// Empty uncompressed block, that is appended by `Z_SYNC_FLUSH`.
+const TRAILER: [u8; 4] = [0, 0, 0xff, 0xff];
+
+/// `permessage-deflate` extension configuration, That is used to negotiate the extension.
+///
+/// Parameters are named after the endpoint they apply to, As in the RFC.
+///
+/// - Client side: the parameters are offered to the server.
+/// - Server side: the parameters are applied on top of the client's offer.
+#[derive(Debug, Clone)]
+pub struct PerMessageDeflate {
+    /// Compression level, From `0` (no compression) to `9` (best compression).
+    ///
+    /// Default: `6`
+    pub level: u32,
+
+    /// Server must reset its compression context after each message.
+    ///
+    /// Default: `false`
+    pub server_no_context_takeover: bool,
+
+    /// Client must reset its compression context after each message.
+    ///
+    /// Default: `false`
+    pub client_no_context_takeover: bool,
+
+    /// Limits the LZ77 sliding window size (as base-2 logarithm: `9..=15`), that is used by the server to compress messages.
+    ///
+    /// Default: `None` (`15`)
+    pub server_max_window_bits: Option<u8>,
+
+    /// Limits the LZ77 sliding window size (as base-2 logarithm: `9..=15`), that is used by the client to compress messages.
+    ///
+    /// Default: `None` (`15`)
+    pub client_max_window_bits: Option<u8>,
+
+    /// Messages smaller than `threshold` bytes are sent uncompressed.
+    ///
+    /// Default: `32`
+    pub threshold: usize,
+
+    /// Maximum length of a decompressed frame payload.
+    ///
+    /// Default: 16 MB
+    pub max_decompressed_len: usize,
+}
+
+impl Default for PerMessageDeflate {
+    fn default() -> Self {
+        Self {
+            level: 6,
+            server_no_context_takeover: false,
+            client_no_context_takeover: false,
+            server_max_window_bits: None,
+            client_max_window_bits: None,
+            threshold: 32,
+            max_decompressed_len: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// Negotiated parameters.
+#[derive(Default)]
+struct Params {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    server_max_window_bits: Option<Option<u8>>,
+    client_max_window_bits: Option<Option<u8>>,
+}
+
+impl Params {
+    fn parse(offer: &Offer) -> std::result::Result<Self, &'static str> {
+        fn flag(slot: &mut bool, value: &Option<String>) -> std::result::Result<(), &'static str> {
+            if *slot || value.is_some() {
+                return Err("invalid `permessage-deflate` parameter");
+            }
+            *slot = true;
+            Ok(())
+        }
+        fn window_bits(
+            slot: &mut Option<Option<u8>>,
+            value: &Option<String>,
+        ) -> std::result::Result<(), &'static str> {
+            if slot.is_some() {
+                return Err("duplicate `permessage-deflate` parameter");
+            }
+            *slot = Some(match value {
+                None => None,
+                Some(value) => match value.parse() {
+                    // leading zeros are not allowed: `1*DIGIT` with value `8..=15`
+                    Ok(bits @ 8..=15) if !value.starts_with('0') => Some(bits),
+                    _ => return Err("invalid `permessage-deflate` window bits"),
+                },
+            });
+            Ok(())
+        }
+        let mut params = Self::default();
+        for (key, value) in &offer.params {
+            match key.to_ascii_lowercase().as_str() {
+                "server_no_context_takeover" => {
+                    flag(&mut params.server_no_context_takeover, value)?
+                }
+                "client_no_context_takeover" => {
+                    flag(&mut params.client_no_context_takeover, value)?
+                }
+                "server_max_window_bits" => window_bits(&mut params.server_max_window_bits, value)?,
+                "client_max_window_bits" => window_bits(&mut params.client_max_window_bits, value)?,
+                _ => return Err("unknown `permessage-deflate` parameter"),
+            }
+        }
+        Ok(params)
+    }
+}
+
+/// zlib supports window size from `9` to `15` bits.
+fn window_bits(bits: Option<u8>) -> Option<u8> {
+    bits.map(|bits| bits.clamp(9, 15))
+}
+
+impl PerMessageDeflate {
+    fn extension(
+        &self,
+        window_bits: u8,
+        no_context_takeover: bool,
+        peer_no_context_takeover: bool,
+    ) -> Box<dyn Extension> {
+        Box::new(Deflate {
+            window_bits,
+            compress: Compress::new_with_window_bits(
+                Compression::new(self.level.min(9)),
+                false,
+                window_bits,
+            ),
+            compress_reset: no_context_takeover,
+            threshold: self.threshold,
+            compressing: false,
+            inflate: Some(Inflate {
+                decompress: Decompress::new_with_window_bits(false, 15),
+                decompress_reset: peer_no_context_takeover,
+                max_decompressed_len: self.max_decompressed_len,
+                decompressing: false,
+            }),
+        })
+    }
+
+    /// Compress a message ahead of time without context takeover, Using `server_max_window_bits` (if any).
+    ///
+    /// Returns the parameters, that are checked by [Extension::accept_prepared].
+    pub(crate) fn encode_prepared(&self, frame: &mut DataFrame) -> Result<Offer> {
+        let bits = window_bits(self.server_max_window_bits).unwrap_or(15);
+        self.extension(bits, true, true).encode(frame)?;
+        Ok(Offer::new(NAME).with_param("max_window_bits", Some(bits.to_string())))
+    }
+}
+
+impl Negotiator for PerMessageDeflate {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn offer(&self) -> Vec<Offer> {
+        let mut offer = Offer::new(NAME);
+        if self.server_no_context_takeover {
+            offer = offer.with_param("server_no_context_takeover", None::<String>);
+        }
+        if self.client_no_context_takeover {
+            offer = offer.with_param("client_no_context_takeover", None::<String>);
+        }
+        if let Some(bits) = window_bits(self.server_max_window_bits) {
+            offer = offer.with_param("server_max_window_bits", Some(bits.to_string()));
+        }
+        // Indicates that the client supports `client_max_window_bits` in the response.
+        let bits = window_bits(self.client_max_window_bits).map(|bits| bits.to_string());
+        vec![offer.with_param("client_max_window_bits", bits)]
+    }
+
+    fn accept(&self, offers: &[Offer]) -> Option<(Offer, Box<dyn Extension>)> {
+        offers.iter().find_map(|offer| {
+            let params = Params::parse(offer).ok()?;
+            let mut response = Offer::new(NAME);
+
+            let server_no_context_takeover =
+                params.server_no_context_takeover || self.server_no_context_takeover;
+            if server_no_context_takeover {
+                response = response.with_param("server_no_context_takeover", None::<String>);
+            }
+            let client_no_context_takeover =
+                params.client_no_context_takeover || self.client_no_context_takeover;
+            if client_no_context_takeover {
+                response = response.with_param("client_no_context_takeover", None::<String>);
+            }
+
+            let offered = params.server_max_window_bits.flatten();
+            // zlib does not support a window size of 256 bytes, So decline the offer.
+            if offered == Some(8) {
+                return None;
+            }
+            let server_max_window_bits = match (offered, window_bits(self.server_max_window_bits)) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            if let Some(bits) = server_max_window_bits {
+                response = response.with_param("server_max_window_bits", Some(bits.to_string()));
+            }
+
+            // The server may limit the client's window, Only if the client supports it.
+            if let Some(offered) = params.client_max_window_bits {
+                let bits = match (offered, window_bits(self.client_max_window_bits)) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (None, b) => b,
+                    _ => None,
+                };
+                if let Some(bits) = bits {
+                    response =
+                        response.with_param("client_max_window_bits", Some(bits.to_string()));
+                }
+            }
+
+            let ext = self.extension(
+                server_max_window_bits.unwrap_or(15),
+                server_no_context_takeover,
+                client_no_context_takeover,
+            );
+            Some((response, ext))
+        })
+    }
+
+    fn confirm(&self, response: &Offer) -> std::result::Result<Box<dyn Extension>, &'static str> {
+        let params = Params::parse(response)?;
+        match params.server_max_window_bits {
+            Some(None) => return Err("`server_max_window_bits` must have a value"),
+            Some(Some(bits)) if bits > window_bits(self.server_max_window_bits).unwrap_or(15) => {
+                return Err("`server_max_window_bits` exceeds the offered value")
+            }
+            _ => {}
+        }
+        if self.server_no_context_takeover && !params.server_no_context_takeover {
+            return Err("expected `server_no_context_takeover` parameter");
+        }
+        let window_bits = match params.client_max_window_bits {
+            None => window_bits(self.client_max_window_bits).unwrap_or(15),
+            Some(None) => return Err("`client_max_window_bits` must have a value"),
+            Some(Some(8)) => return Err("`client_max_window_bits=8` is not supported"),
+            Some(Some(bits)) => bits.min(window_bits(self.client_max_window_bits).unwrap_or(15)),
+        };
+        Ok(self.extension(
+            window_bits,
+            params.client_no_context_takeover || self.client_no_context_takeover,
+            params.server_no_context_takeover,
+        ))
+    }
+}
+
+/// Negotiated `permessage-deflate` extension.
+struct Deflate {
+    /// LZ77 sliding window size of the compressor.
+    window_bits: u8,
+    compress: Compress,
+    /// Reset compression context after each message.
+    compress_reset: bool,
+    threshold: usize,
+    /// Whether the outgoing message is compressed.
+    compressing: bool,
+    /// Decompression state, It is taken out by [Extension::split_decoder].
+    inflate: Option<Inflate>,
+}
+
+/// Decompression state of `permessage-deflate` extension.
+struct Inflate {
+    decompress: Decompress,
+    /// Reset decompression context after each message.
+    decompress_reset: bool,
+    max_decompressed_len: usize,
+    /// Whether the incoming message is compressed.
+    decompressing: bool,
+}
+
+impl Extension for Deflate {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn rsv(&self) -> u8 {
+        RSV1
+    }
+
+    fn encode(&mut self, frame: &mut DataFrame) -> Result<()> {
+        if frame.opcode != 0 {
+            // The size of a fragmented message is unknown, So it is always compressed.
+            self.compressing = !frame.fin || frame.data.len() >= self.threshold;
+            if self.compressing {
+                // `RSV1` is set on the first frame of a compressed message only.
+                frame.rsv |= RSV1;
+            }
+        }
+        if !self.compressing {
+            return Ok(());
+        }
+        let mut data = self.compress(&frame.data)?;
+        if frame.fin {
+            // Remove 4 octets (that are `0x00 0x00 0xff 0xff`) from the tail end.
+            data.truncate(data.len().saturating_sub(TRAILER.len()));
+            if self.compress_reset {
+                self.compress.reset();
+            }
+        }
+        frame.data = data;
+        Ok(())
+    }
+
+    fn decode(&mut self, frame: &mut DataFrame) -> Result<()> {
+        match &mut self.inflate {
+            Some(inflate) => inflate.decode(frame),
+            None => Ok(()),
+        }
+    }
+
+    fn split_decoder(&mut self) -> Option<Box<dyn Extension>> {
+        let inflate = self.inflate.take()?;
+        Some(Box::new(inflate))
+    }
+
+    fn accept_prepared(&mut self, params: &Offer) -> bool {
+        let bits = params.param("max_window_bits").flatten();
+        let bits = bits.and_then(|bits| bits.parse::<u8>().ok());
+        if params.name != NAME || bits.is_none_or(|bits| bits > self.window_bits) {
+            return false;
+        }
+        // The message isn't compressed with our context, So the next message must not refer to it.
+        self.compress.reset();
+        true
+    }
+}
+
+impl Deflate {
+    fn compress(&mut self, input: &[u8]) -> Result<Vec<u8>> {
+        let start = self.compress.total_in();
+        let mut output = Vec::with_capacity(input.len() / 2 + 64);
+        loop {
+            let consumed = (self.compress.total_in() - start) as usize;
+            self.compress
+                .compress_vec(&input[consumed..], &mut output, FlushCompress::Sync)
+                .map_err(Error::other)?;
+
+            let consumed = (self.compress.total_in() - start) as usize;
+            // Flushing is complete, when there is still room in the output buffer.
+            if consumed == input.len() && output.len() < output.capacity() {
+                return Ok(output);
+            }
+            output.reserve(output.capacity());
+        }
+    }
+}
+
+impl Extension for Inflate {
+    fn name(&self) -> &str {
+        NAME
+    }
+
+    fn rsv(&self) -> u8 {
+        RSV1
+    }
+
+    /// Outgoing frames are compressed by [Deflate], That this state was split from.
+    fn encode(&mut self, _: &mut DataFrame) -> Result<()> {
+        Ok(())
+    }
+
+    fn decode(&mut self, frame: &mut DataFrame) -> Result<()> {
+        if frame.opcode != 0 {
+            self.decompressing = frame.rsv & RSV1 != 0;
+        } else if frame.rsv & RSV1 != 0 {
+            // `RSV1` must be `0` on continuation frames.
+            return Err(ProtocolError::ReservedBits.into());
+        }
+        frame.rsv &= !RSV1;
+        if !self.decompressing {
+            return Ok(());
+        }
+        if frame.fin {
+            frame.data.extend_from_slice(&TRAILER);
+        }
+        frame.data = self.decompress(&frame.data)?;
+        if frame.fin && self.decompress_reset {
+            self.decompress.reset(false);
+        }
+        Ok(())
+    }
+}
+
+impl Inflate {
+    fn decompress(&mut self, input: &[u8]) -> Result<Vec<u8>> {
+        let start = self.decompress.total_in();
+        let mut output = Vec::with_capacity((input.len() * 2).min(self.max_decompressed_len) + 64);
+        loop {
+            let consumed = (self.decompress.total_in() - start) as usize;
+            let status = self
+                .decompress
+                .decompress_vec(&input[consumed..], &mut output, FlushDecompress::Sync)
+                .map_err(|_| ProtocolError::InvalidCompressedData)?;
+
+            if output.len() > self.max_decompressed_len {
+                return Err(ProtocolError::MessageTooLarge.into());
+            }
+            let consumed = (self.decompress.total_in() - start) as usize;
+            match status {
+                // The peer has set `BFINAL` bit, So the next message starts with a new context.
+                Status::StreamEnd => {
+                    self.decompress.reset(false);
+                    return Ok(output);
+                }
+                _ if consumed == input.len() && output.len() < output.capacity() => {
+                    return Ok(output)
+                }
+                // no progress
+                Status::BufError if output.len() < output.capacity() => {
+                    return Err(ProtocolError::InvalidCompressedData.into())
+                }
+                _ => output.reserve(output.capacity()),
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! WebSocket over [embedded-io-async](embedded_io_async) streams, Behind `embedded-io-async` feature.
+//!
+//! It doesn't need `std` (only `alloc`), So it runs on embedded targets. For example: over an `embassy-net` TCP socket.
+//! Frames are written immediately (there is no write queue), And there is no timer. So use the timer of your executor,
+//! To limit the wait of `recv` or `close`.
+//!
+//! Without `std`, Client masking-keys are read from [getrandom](https://docs.rs/getrandom/0.2),
+//! That needs a [custom implementation](https://docs.rs/getrandom/0.2/getrandom/macro.register_custom_getrandom.html) on targets without an operating system.
+//! For example: backed by the hardware RNG of the microcontroller. `getrandom` doesn't even compile on those targets,
+//! Unless your crate enables its `custom` feature:
+//!
+//! ```toml
+//! getrandom = { version = "0.2", features = ["custom"] }
+//! ```
+//!
+//! See [embedded-example](https://github.com/nurmohammed840/websocket.rs/tree/master/examples/embedded-example) for a complete setup.
+//!
+//! ### Example
+//!
+//! ```no_run
+//! use embedded_io_async::{Read, Write};
+//! use web_socket::{embedded::WebSocket, Event};
+//!
+//! # async fn run<IO: Read + Write>(stream: IO) -> web_socket::io::Result<()> {
+//! let mut ws = WebSocket::client(stream);
+//! ws.send("Hello").await?;
+//! loop {
+//!     match ws.recv().await? {
+//!         Event::Data { data, .. } => ws.send(&*data).await?,
+//!         Event::Ping(data) => ws.send_pong(data).await?,
+//!         Event::Pong(_) => {}
+//!         Event::Error(_) | Event::Close { .. } => return ws.close(()).await,
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::{
+    io::{Error, ErrorKind, Result},
+    *,
+};
+use alloc::format;
+use embedded_io_async::{Read, Write};
+
+/// WebSocket implementation for both client and server, Over `embedded-io-async` streams.
+///
+/// Protocol state and its configuration (For example: [Connection::max_payload_len]) are accessible through `Deref`.
+#[derive(Debug)]
+pub struct WebSocket<Stream> {
+    /// Underlying byte stream over which websocket messages are exchanged.
+    pub stream: Stream,
+    conn: Connection,
+    /// Received `Close` (or `Error`) event, That is returned once the `Close` frame is written in reply.
+    closing: Option<Event>,
+}
+
+impl_websocket!(closing: None);
+
+impl<W: Write> WebSocket<W> {
+    impl_send!(async);
+
+    /// Write the encoded frames.
+    ///
+    /// Written bytes are tracked by the connection, So it resumes after an error.
+    async fn write_outgoing(&mut self) -> Result<()> {
+        loop {
+            let outgoing = self.conn.outgoing();
+            if outgoing.is_empty() {
+                return Ok(());
+            }
+            match self.stream.write(outgoing).await {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(amt) => self.conn.consume_outgoing(amt),
+                Err(err) => return Err(io_error(err)),
+            }
+        }
+    }
+
+    #[inline]
+    async fn flush_stream(&mut self) -> Result<()> {
+        self.stream.flush().await.map_err(io_error)
+    }
+}
+
+impl<IO: Read + Write> WebSocket<IO> {
+    impl_recv! {
+        async;
+        /// reads [Event] from websocket stream.
+        ///
+        /// Partially received frame is kept in the websocket, So if the future is dropped (For example: on a timeout)
+        /// The next call resumes reading exactly where it stopped, As long as `read` of the stream is cancellation safe.
+        fn recv;
+    }
+
+    impl_close! {
+        async;
+        /// Use the timer of your executor (For example: `embassy_time::with_timeout`), To limit the wait.
+        /// `Write` has no way to shutdown a stream, So it is flushed. And closed once it is dropped.
+    }
+
+    #[inline]
+    async fn wait_for_close(&mut self) -> Result<()> {
+        self.recv_close().await
+    }
+
+    #[inline]
+    async fn shutdown(&mut self) -> Result<()> {
+        self.flush_stream().await
+    }
+
+    /// Read some bytes from the stream, Into the read buffer of the connection.
+    async fn fill(&mut self) -> Result<()> {
+        match self.stream.read(self.conn.read_buf()).await {
+            Ok(0) => {
+                self.conn.fail();
+                Err(ErrorKind::UnexpectedEof.into())
+            }
+            Ok(amt) => {
+                self.conn.advance(amt);
+                Ok(())
+            }
+            Err(err) => {
+                self.conn.fail();
+                Err(io_error(err))
+            }
+        }
+    }
+}
+
+/// Convert an error of the stream, Into [Error] of the corresponding kind.
+fn io_error<E: embedded_io_async::Error>(err: E) -> Error {
+    use embedded_io_async::ErrorKind as Kind;
+    let kind = match err.kind() {
+        Kind::NotFound => ErrorKind::NotFound,
+        Kind::PermissionDenied => ErrorKind::PermissionDenied,
+        Kind::ConnectionRefused => ErrorKind::ConnectionRefused,
+        Kind::ConnectionReset => ErrorKind::ConnectionReset,
+        Kind::ConnectionAborted => ErrorKind::ConnectionAborted,
+        Kind::NotConnected => ErrorKind::NotConnected,
+        Kind::AddrInUse => ErrorKind::AddrInUse,
+        Kind::AddrNotAvailable => ErrorKind::AddrNotAvailable,
+        Kind::BrokenPipe => ErrorKind::BrokenPipe,
+        Kind::AlreadyExists => ErrorKind::AlreadyExists,
+        Kind::InvalidInput => ErrorKind::InvalidInput,
+        Kind::InvalidData => ErrorKind::InvalidData,
+        Kind::TimedOut => ErrorKind::TimedOut,
+        Kind::Interrupted => ErrorKind::Interrupted,
+        Kind::Unsupported => ErrorKind::Unsupported,
+        Kind::OutOfMemory => ErrorKind::OutOfMemory,
+        Kind::WriteZero => ErrorKind::WriteZero,
+        _ => ErrorKind::Other,
+    };
+    Error::new(kind, format!("{err:?}"))
+}
@@ -0,0 +1,119 @@
+use crate::CloseCode;
+#[cfg(feature = "alloc")]
+use crate::{io, CloseReason};
+#[cfg(feature = "alloc")]
+use alloc::{string::ToString, vec::Vec};
+use core::fmt;
+
+/// A violation of the websocket protocol by the remote endpoint, that fails the connection.
+///
+/// ### Example
+///
+/// ```rust
+/// use web_socket::{CloseCode, ProtocolError};
+///
+/// let err = ProtocolError::InvalidUtf8;
+/// assert_eq!(err.close_code(), CloseCode::InvalidPayload);
+/// assert_eq!(err.to_string(), "invalid utf-8 payload");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProtocolError {
+    /// Reserved bits are set, That are not defined by any negotiated extension.
+    ReservedBits,
+    /// A client sent an unmasked frame.
+    UnmaskedFrame,
+    /// A server sent a masked frame.
+    MaskedFrame,
+    /// A control frame is fragmented.
+    FragmentedControlFrame,
+    /// A control frame has a payload length of more than 125 bytes.
+    ControlFrameTooLarge,
+    /// Opcode is reserved for further use.
+    UnknownOpcode,
+    /// A continuation frame without a started message, Or a new message before the previous one is finished.
+    InvalidContinuation,
+    /// Payload length exceeds the configured limit.
+    PayloadTooLarge,
+    /// Message length, Across all fragments, Exceeds the configured limit.
+    MessageTooLarge,
+    /// 64-bit payload length has the most significant bit set.
+    InvalidLength,
+    /// Payload length doesn't fit in `usize` on this target.
+    LengthOverflow,
+    /// Payload length is not encoded in the minimal number of bytes.
+    ///
+    /// It is reported only if [Connection::strict_length_encoding](crate::Connection::strict_length_encoding) is enabled.
+    NonMinimalLength,
+    /// `Close` frame has an invalid status code, Or a payload of a single byte.
+    InvalidCloseCode,
+    /// Text message or close reason is not valid UTF-8.
+    InvalidUtf8,
+    /// A compressed message can't be decompressed, By a negotiated extension.
+    InvalidCompressedData,
+}
+
+impl ProtocolError {
+    /// Returns the status code of the `Close` frame, that fails the connection.
+    pub fn close_code(&self) -> CloseCode {
+        match self {
+            ProtocolError::PayloadTooLarge
+            | ProtocolError::MessageTooLarge
+            | ProtocolError::LengthOverflow => CloseCode::MessageTooBig,
+            ProtocolError::InvalidUtf8 | ProtocolError::InvalidCompressedData => {
+                CloseCode::InvalidPayload
+            }
+            _ => CloseCode::ProtocolError,
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ProtocolError::ReservedBits => "reserve bit must be `0`",
+            ProtocolError::UnmaskedFrame => "expected masked frame",
+            ProtocolError::MaskedFrame => "expected unmasked frame",
+            ProtocolError::FragmentedControlFrame => "control frame must not be fragmented",
+            ProtocolError::ControlFrameTooLarge => {
+                "control frame must have a payload length of 125 bytes or less"
+            }
+            ProtocolError::UnknownOpcode => "unknown opcode",
+            ProtocolError::InvalidContinuation => "invalid data frame",
+            ProtocolError::PayloadTooLarge => "payload too large",
+            ProtocolError::MessageTooLarge => "message too large",
+            ProtocolError::InvalidLength => "most significant bit of payload length must be `0`",
+            ProtocolError::LengthOverflow => "payload length overflows `usize`",
+            ProtocolError::NonMinimalLength => "payload length must be minimally encoded",
+            ProtocolError::InvalidCloseCode => "invalid close code",
+            ProtocolError::InvalidUtf8 => "invalid utf-8 payload",
+            ProtocolError::InvalidCompressedData => "invalid compressed data",
+        })
+    }
+}
+
+impl core::error::Error for ProtocolError {}
+
+#[cfg(feature = "alloc")]
+impl From<ProtocolError> for io::Error {
+    fn from(err: ProtocolError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl ProtocolError {
+    /// Returns the protocol error, That is wrapped by `err`. (For example: returned by an [Extension](crate::extension::Extension))
+    pub(crate) fn from_io(err: &io::Error) -> Option<Self> {
+        err.get_ref()?.downcast_ref().copied()
+    }
+}
+
+/// `Close` frame with the corresponding status code, and the error message as reason.
+#[cfg(feature = "alloc")]
+impl CloseReason for ProtocolError {
+    type Bytes = Vec<u8>;
+    fn to_bytes(self) -> Self::Bytes {
+        CloseReason::to_bytes((self.close_code(), self.to_string()))
+    }
+}
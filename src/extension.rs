@@ -0,0 +1,315 @@
+//! # WebSocket extensions
+//!
+//! Extensions are negotiated with `Sec-WebSocket-Extensions` header during the handshake, As defined in [RFC 6455 section 9.1](https://datatracker.ietf.org/doc/html/rfc6455#section-9.1)
+//!
+//! ```yml
+//! Sec-WebSocket-Extensions: permessage-deflate; client_max_window_bits, x-foo; bar="baz"
+//! ```
+//!
+//! - A client offers extensions with [Negotiator::offer], In order of preference.
+//! - A server accepts (at most one offer of) an extension with [Negotiator::accept].
+//! - A client validates the accepted extension with [Negotiator::confirm].
+//!
+//! Once negotiated, An [Extension] may use reserved bits (`RSV1`, `RSV2`, `RSV3`) and transform payload of data frames.
+//! Extensions are applied in the order they were negotiated when sending, and in reverse order when receiving.
+
+use crate::io;
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+
+/// `RSV1` bit of the first byte of frame header.
+pub const RSV1: u8 = 0b_0100_0000;
+/// `RSV2` bit of the first byte of frame header.
+pub const RSV2: u8 = 0b_0010_0000;
+/// `RSV3` bit of the first byte of frame header.
+pub const RSV3: u8 = 0b_0001_0000;
+
+/// An extension with its parameters, as it appear in `Sec-WebSocket-Extensions` header.
+///
+/// It represent both offer (sent by client) and response (sent by server).
+///
+/// ### Example
+///
+/// ```rust
+/// use web_socket::extension::Offer;
+///
+/// let offers = Offer::parse(r#"permessage-deflate; client_max_window_bits, x-foo; bar="baz""#).unwrap();
+/// assert_eq!(offers[0].name, "permessage-deflate");
+/// assert_eq!(offers[0].param("client_max_window_bits"), Some(None));
+/// assert_eq!(offers[1].param("bar"), Some(Some("baz")));
+///
+/// assert_eq!(Offer::to_header(&offers), "permessage-deflate; client_max_window_bits, x-foo; bar=baz");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Offer {
+    /// Extension name, For example: `permessage-deflate`
+    pub name: String,
+    /// Extension parameters, in the order they appear.
+    pub params: Vec<(String, Option<String>)>,
+}
+
+impl Offer {
+    /// Create an extension offer without any parameter.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            params: vec![],
+        }
+    }
+
+    /// Add a parameter, `value` is optional.
+    pub fn with_param(mut self, key: impl Into<String>, value: Option<impl Into<String>>) -> Self {
+        self.params.push((key.into(), value.map(Into::into)));
+        self
+    }
+
+    /// Returns the value of the first parameter named `key`. (case-insensitive)
+    ///
+    /// `Some(None)` means the parameter is present without a value.
+    pub fn param(&self, key: &str) -> Option<Option<&str>> {
+        self.params
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_deref())
+    }
+
+    /// Parse `Sec-WebSocket-Extensions` header value, Which is a comma separated list of extensions.
+    pub fn parse(header: &str) -> Result<Vec<Offer>, ParseError> {
+        let mut parser = Parser {
+            bytes: header.as_bytes(),
+            pos: 0,
+        };
+        let mut offers = vec![];
+        loop {
+            parser.skip_ws();
+            match parser.peek() {
+                None => break,
+                // empty list elements are allowed: `#rule`
+                Some(b',') => {
+                    parser.pos += 1;
+                    continue;
+                }
+                Some(_) => {}
+            }
+            let mut offer = Offer::new(parser.token()?);
+            loop {
+                parser.skip_ws();
+                match parser.peek() {
+                    Some(b';') => parser.pos += 1,
+                    Some(b',') | None => break,
+                    Some(_) => return Err(ParseError("expected `;` or `,`")),
+                }
+                parser.skip_ws();
+                let key = parser.token()?;
+                parser.skip_ws();
+                let value = if parser.peek() == Some(b'=') {
+                    parser.pos += 1;
+                    parser.skip_ws();
+                    Some(parser.value()?)
+                } else {
+                    None
+                };
+                offer.params.push((key, value));
+            }
+            offers.push(offer);
+        }
+        if offers.is_empty() {
+            return Err(ParseError("expected at least one extension"));
+        }
+        Ok(offers)
+    }
+
+    /// Format a list of extensions as `Sec-WebSocket-Extensions` header value.
+    pub fn to_header(offers: &[Offer]) -> String {
+        offers
+            .iter()
+            .map(Offer::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl fmt::Display for Offer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        for (key, value) in &self.params {
+            match value {
+                Some(value) if value.bytes().all(is_tchar) && !value.is_empty() => {
+                    write!(f, "; {key}={value}")?
+                }
+                Some(value) => {
+                    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+                    write!(f, "; {key}=\"{value}\"")?
+                }
+                None => write!(f, "; {key}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when `Sec-WebSocket-Extensions` header is malformed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(pub &'static str);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid `Sec-WebSocket-Extensions` header: {}", self.0)
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+/// A data frame, that is passed through negotiated [Extension]s.
+#[derive(Debug)]
+pub struct DataFrame {
+    /// Indicates that this is the final fragment in a message.
+    pub fin: bool,
+    /// Reserved bits (`RSV1`, `RSV2`, `RSV3`) of the frame.
+    pub rsv: u8,
+    /// `1` (text) or `2` (binary) for the first frame of a message, `0` for continuation frames.
+    pub opcode: u8,
+    /// Payload of the frame.
+    pub data: Vec<u8>,
+}
+
+/// A negotiated extension, that transform payload of data frames.
+///
+/// Control frames are not passed through extensions.
+pub trait Extension: Send {
+    /// Extension name, For example: `permessage-deflate`
+    fn name(&self) -> &str;
+
+    /// Reserved bits (`RSV1`, `RSV2`, `RSV3`) that are used by this extension.
+    ///
+    /// A frame received with any reserved bit, That isn't used by a negotiated extension, fails the connection.
+    fn rsv(&self) -> u8 {
+        0
+    }
+
+    /// Transform an outgoing data frame, Before it is sent.
+    fn encode(&mut self, frame: &mut DataFrame) -> io::Result<()>;
+
+    /// Transform an incoming data frame, After it is received.
+    ///
+    /// A [ProtocolError](crate::ProtocolError) (converted into `io::Error`) fails the connection with a `Close` frame
+    /// of the corresponding status code, Like any other protocol error. Other errors fail the connection without it.
+    fn decode(&mut self, frame: &mut DataFrame) -> io::Result<()>;
+
+    /// Take the state of incoming frames (For example: decompression context) out of this extension, Into a separate extension.
+    ///
+    /// It is called when a websocket is split, So that the read half decodes frames without blocking the write half.
+    /// Afterwards this extension only encodes outgoing frames, And the returned one only decodes incoming frames.
+    ///
+    /// Returns `None` (by default), If the state can't be separated. Then the halves share this extension.
+    fn split_decoder(&mut self) -> Option<Box<dyn Extension>> {
+        None
+    }
+
+    /// Whether a message, That was encoded ahead of time by an extension of the same name with `params`, can be sent as is.
+    /// (See [PreparedFrame](crate::PreparedFrame))
+    ///
+    /// If it returns `true`, The extension should discard any state (For example: compression context),
+    /// That is shared between outgoing messages. Because the message is not passed through [Extension::encode].
+    fn accept_prepared(&mut self, _params: &Offer) -> bool {
+        false
+    }
+}
+
+impl fmt::Debug for dyn Extension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Extension").field(&self.name()).finish()
+    }
+}
+
+/// Negotiate an [Extension] during the handshake.
+pub trait Negotiator: Send + Sync {
+    /// Extension name, For example: `permessage-deflate`
+    fn name(&self) -> &str;
+
+    /// Client side: Returns the offers that are sent with the request, In order of preference.
+    fn offer(&self) -> Vec<Offer>;
+
+    /// Server side: Accept one of the `offers` (having the same name), that are sent by the client in order of preference.
+    ///
+    /// Returns the response, that is sent back to the client and the negotiated extension,
+    /// Or `None` to decline the extension.
+    fn accept(&self, offers: &[Offer]) -> Option<(Offer, Box<dyn Extension>)>;
+
+    /// Client side: Validate the `response`, that was accepted by the server and returns the negotiated extension.
+    fn confirm(&self, response: &Offer) -> Result<Box<dyn Extension>, &'static str>;
+}
+
+/// Returns reserved bits that are used by `extensions`.
+pub(crate) fn rsv_bits(extensions: &[Box<dyn Extension>]) -> u8 {
+    extensions.iter().fold(0, |bits, ext| bits | ext.rsv())
+}
+
+// ------------------------------------------------------------------------
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(b' ' | b'\t') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn token(&mut self) -> Result<String, ParseError> {
+        let start = self.pos;
+        while self.peek().is_some_and(is_tchar) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(ParseError("expected token"));
+        }
+        Ok(String::from_utf8_lossy(&self.bytes[start..self.pos]).into())
+    }
+
+    fn value(&mut self) -> Result<String, ParseError> {
+        if self.peek() != Some(b'"') {
+            return self.token();
+        }
+        self.pos += 1;
+        let mut value = vec![];
+        loop {
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    self.pos += 1;
+                    value.push(
+                        self.peek()
+                            .ok_or(ParseError("unterminated quoted-string"))?,
+                    );
+                }
+                Some(byte) => value.push(byte),
+                None => return Err(ParseError("unterminated quoted-string")),
+            }
+            self.pos += 1;
+        }
+        self.pos += 1;
+        // the value after quoted-string unescaping MUST conform to the `token` ABNF.
+        if value.is_empty() || !value.iter().copied().all(is_tchar) {
+            return Err(ParseError("quoted value must be a token"));
+        }
+        Ok(String::from_utf8_lossy(&value).into())
+    }
+}
+
+/// `tchar` as defined in [RFC 7230 section 3.2.6](https://datatracker.ietf.org/doc/html/rfc7230#section-3.2.6)
+fn is_tchar(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
@@ -1,4 +1,10 @@
 #![doc(hidden)]
+#![allow(clippy::unusual_byte_groupings)]
+
+#[cfg(feature = "alloc")]
+use crate::{extension::DataFrame, mask, ProtocolError, Role};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
 
 pub struct Frame<'a> {
     pub fin: bool,
@@ -7,22 +13,24 @@ pub struct Frame<'a> {
 }
 
 impl<'a> Frame<'a> {
+    #[cfg(feature = "alloc")]
     #[inline]
     pub fn encode_without_mask(self) -> Vec<u8> {
         let mut buf = Vec::<u8>::with_capacity(10 + self.data.len());
         unsafe {
             let dist = buf.as_mut_ptr();
             let head_len = self.encode_header_unchecked(dist, 0);
-            std::ptr::copy_nonoverlapping(self.data.as_ptr(), dist.add(head_len), self.data.len());
+            core::ptr::copy_nonoverlapping(self.data.as_ptr(), dist.add(head_len), self.data.len());
             buf.set_len(head_len + self.data.len());
         }
         buf
     }
 
+    #[cfg(feature = "alloc")]
     #[inline]
     pub fn encode_with(self, mask: [u8; 4]) -> Vec<u8> {
         let mut buf = Vec::<u8>::with_capacity(14 + self.data.len());
-        unsafe {
+        let head_len = unsafe {
             let dist = buf.as_mut_ptr();
             let head_len = self.encode_header_unchecked(dist, 0x80);
 
@@ -33,16 +41,31 @@ impl<'a> Frame<'a> {
             dist.add(head_len + 3).write(d);
 
             let dist = dist.add(head_len + 4);
-            // TODO: Use SIMD wherever possible for best performance
-            for i in 0..self.data.len() {
-                dist.add(i)
-                    .write(self.data.get_unchecked(i) ^ mask.get_unchecked(i & 3));
-            }
+            core::ptr::copy_nonoverlapping(self.data.as_ptr(), dist, self.data.len());
             buf.set_len(head_len + 4 + self.data.len());
-        }
+            head_len
+        };
+        mask::apply_mask(&mut buf[head_len + 4..], mask);
         buf
     }
 
+    /// Encode frame header into `head`, Returns the header length.
+    ///
+    /// If `mask` is `Some`, The masking-key is appended to the header. But the payload is not masked, (See [mask::apply_mask](crate::mask::apply_mask))
+    #[inline]
+    pub fn encode_header(&self, mask: Option<[u8; 4]>, head: &mut [u8; 14]) -> usize {
+        let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+        // SAFETY: `head` is valid for writes of 14 bytes.
+        let head_len = unsafe { self.encode_header_unchecked(head.as_mut_ptr(), mask_bit) };
+        match mask {
+            Some(key) => {
+                head[head_len..head_len + 4].copy_from_slice(&key);
+                head_len + 4
+            }
+            None => head_len,
+        }
+    }
+
     /// # SEAFTY
     ///
     /// - `dist` must be valid for writes of 10 bytes.
@@ -94,3 +117,89 @@ impl<'a> From<&'a [u8]> for Frame<'a> {
         }
     }
 }
+
+#[cfg(feature = "alloc")]
+impl<'a> From<&'a DataFrame> for Frame<'a> {
+    /// Reserved bits are encoded along with opcode.
+    #[inline]
+    fn from(frame: &'a DataFrame) -> Self {
+        Self {
+            fin: frame.fin,
+            opcode: frame.rsv | frame.opcode,
+            data: &frame.data,
+        }
+    }
+}
+
+/// Check the first two bytes of a frame header, That is received by `role`.
+///
+/// Reserved bits in `rsv_bits` are allowed in data frames, They are used by negotiated extensions.
+#[cfg(feature = "alloc")]
+pub(crate) fn check_head(role: Role, [b1, b2]: [u8; 2], rsv_bits: u8) -> Result<(), ProtocolError> {
+    let fin = b1 & 0b_1000_0000 != 0;
+    let rsv = b1 & 0b_111_0000;
+    let opcode = b1 & 0b_1111;
+    let len = b2 & 0b_111_1111;
+
+    // Defines whether the "Payload data" is masked.  If set to 1, a
+    // masking key is present in masking-key, and this is used to unmask
+    // the "Payload data" as per [Section 5.3](https://datatracker.ietf.org/doc/html/rfc6455#section-5.3).  All frames sent from
+    // client to server have this bit set to 1.
+    let is_masked = b2 & 0b_1000_0000 != 0;
+
+    if rsv & !rsv_bits != 0 || (rsv != 0 && opcode >= 8) {
+        // MUST be `0` unless an extension is negotiated that defines meanings
+        // for non-zero values.  If a nonzero value is received and none of
+        // the negotiated extensions defines the meaning of such a nonzero
+        // value, the receiving endpoint MUST _Fail the WebSocket Connection_.
+        return Err(ProtocolError::ReservedBits);
+    }
+
+    // A client MUST mask all frames that it sends to the server. (Note
+    // that masking is done whether or not the WebSocket Protocol is running
+    // over TLS.)  The server MUST close the connection upon receiving a
+    // frame that is not masked.
+    //
+    // A server MUST NOT mask any frames that it sends to the client.
+    match role {
+        Role::Server if !is_masked => return Err(ProtocolError::UnmaskedFrame),
+        Role::Client if is_masked => return Err(ProtocolError::MaskedFrame),
+        _ => {}
+    }
+
+    // 3-7 are reserved for further non-control frames.
+    if opcode >= 8 {
+        if !fin {
+            return Err(ProtocolError::FragmentedControlFrame);
+        }
+        if len > 125 {
+            return Err(ProtocolError::ControlFrameTooLarge);
+        }
+    }
+    Ok(())
+}
+
+/// Decode the extended payload length, That is 2 bytes (if 7-bit payload length is `126`) or 8 bytes (if it is `127`).
+///
+/// If `strict` is set, Length must be encoded in the minimal number of bytes.
+#[cfg(feature = "alloc")]
+pub(crate) fn decode_extended_len(bytes: &[u8], strict: bool) -> Result<usize, ProtocolError> {
+    if let [a, b] = *bytes {
+        let len = u16::from_be_bytes([a, b]);
+        if strict && len < 126 {
+            return Err(ProtocolError::NonMinimalLength);
+        }
+        return Ok(len as usize);
+    }
+    let mut buf = [0; 8];
+    buf.copy_from_slice(bytes);
+    let len = u64::from_be_bytes(buf);
+    // The most significant bit must be `0`
+    if len >> 63 != 0 {
+        return Err(ProtocolError::InvalidLength);
+    }
+    if strict && len <= u16::MAX as u64 {
+        return Err(ProtocolError::NonMinimalLength);
+    }
+    usize::try_from(len).map_err(|_| ProtocolError::LengthOverflow)
+}
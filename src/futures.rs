@@ -0,0 +1,340 @@
+//! WebSocket over [futures_io::AsyncRead] and [futures_io::AsyncWrite] streams, Behind `futures-io` feature.
+//!
+//! It is meant for runtimes that don't use `tokio` streams (For example: `async-std`, `smol`, `glommio`).
+//! Both this and the `tokio` [WebSocket](crate::WebSocket) are wrappers over a [Connection], With the same `send` / `recv` API.
+//!
+//! But `futures-io` has neither a timer nor a way to split a stream. So unlike the `tokio` `WebSocket`,
+//! There is no `split` / `reunite`, No `close_timeout` (use the timeout of your runtime),
+//! And frames are not queued (no `write_buffer_size` / `write_delay`), Use [WebSocket::send_batch] instead.
+//!
+//! ### Example
+//!
+//! ```no_run
+//! use futures_io::{AsyncRead, AsyncWrite};
+//! use web_socket::Event;
+//!
+//! # async fn run<IO: Unpin + AsyncRead + AsyncWrite>(stream: IO) -> Result<(), web_socket::handshake::Error> {
+//! let mut ws = web_socket::futures::accept(stream).await?;
+//! loop {
+//!     match ws.recv().await? {
+//!         Event::Data { data, .. } => ws.send(&*data).await?,
+//!         Event::Ping(data) => ws.send_pong(data).await?,
+//!         Event::Pong(_) => {}
+//!         Event::Error(_) | Event::Close { .. } => return Ok(ws.close(()).await?),
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::{
+    handshake::{self, bad_response, parse_head, Error, Head, Request, Response},
+    *,
+};
+use futures_core::{FusedStream, Stream};
+use futures_io::{AsyncRead, AsyncWrite};
+use futures_sink::Sink;
+use std::{
+    future::poll_fn,
+    io::{self, ErrorKind, Result},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+/// WebSocket implementation for both client and server, Over `futures-io` streams.
+///
+/// Protocol state and its configuration (For example: [Connection::max_payload_len]) are accessible through `Deref`.
+#[derive(Debug)]
+pub struct WebSocket<Stream> {
+    /// Underlying byte stream over which websocket messages are exchanged.
+    pub stream: Stream,
+    conn: Connection,
+    /// Received `Close` (or `Error`) event, That is returned once the `Close` frame is written in reply.
+    closing: Option<Event>,
+}
+
+impl_websocket!(closing: None);
+
+impl<W> WebSocket<W>
+where
+    W: Unpin + AsyncWrite,
+{
+    impl_send!(async);
+
+    /// Send multiple messages, That are encoded together and written at once.
+    pub async fn send_batch<'a, I>(&mut self, frames: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Into<Frame<'a>>,
+    {
+        let result = frames
+            .into_iter()
+            .try_for_each(|frame| self.conn.encode(frame));
+        self.flush().await?;
+        result
+    }
+
+    /// Write the encoded frames.
+    ///
+    /// This method is cancellation safe, Written bytes are tracked by the connection.
+    async fn write_outgoing(&mut self) -> Result<()> {
+        poll_fn(|cx| self.poll_write_outgoing(cx)).await
+    }
+
+    #[inline]
+    async fn flush_stream(&mut self) -> Result<()> {
+        poll_fn(|cx| Pin::new(&mut self.stream).poll_flush(cx)).await
+    }
+
+    /// There is no write queue, So the encoded frames are always due.
+    #[inline]
+    fn poll_write_due(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        self.poll_write_outgoing(cx)
+    }
+
+    #[inline]
+    fn encode_message(&mut self, message: OutgoingMessage) -> Result<()> {
+        message.encode(&mut self.conn)
+    }
+
+    #[inline]
+    fn poll_shutdown(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        Pin::new(&mut self.stream).poll_close(cx)
+    }
+
+    fn poll_write_outgoing(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        loop {
+            let outgoing = self.conn.outgoing();
+            if outgoing.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            match ready!(Pin::new(&mut self.stream).poll_write(cx, outgoing)) {
+                Ok(0) => return Poll::Ready(Err(ErrorKind::WriteZero.into())),
+                Ok(amt) => self.conn.consume_outgoing(amt),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+    }
+}
+
+impl<IO> WebSocket<IO>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    impl_poll_recv! {
+        /// reads [Event] from websocket stream.
+        ///
+        /// This method is cancellation safe. Partially received frame is kept in the websocket,
+        /// So the next call resumes reading exactly where it stopped.
+        fn recv;
+        fn recv_message;
+    }
+
+    /// There is no write queue, So no frame is delayed.
+    #[inline]
+    fn poll_delayed(&mut self, _: &mut Context) {}
+
+    /// Write the reply to the received `Close` frame, And flush the stream.
+    fn poll_reply(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        self.conn.echo_close();
+        ready!(self.poll_write_outgoing(cx))?;
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    /// Read some bytes from the stream, Into the read buffer of the connection.
+    fn poll_fill(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        loop {
+            let result = ready!(Pin::new(&mut self.stream).poll_read(cx, self.conn.read_buf()));
+            return Poll::Ready(match result {
+                Ok(0) => {
+                    self.conn.fail();
+                    Err(ErrorKind::UnexpectedEof.into())
+                }
+                Ok(amt) => {
+                    self.conn.advance(amt);
+                    Ok(())
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.conn.fail();
+                    Err(err)
+                }
+            });
+        }
+    }
+}
+
+impl_stream!(IO: Unpin + AsyncRead + AsyncWrite);
+
+impl_sink! {
+    /// `poll_ready` is pending, Until the previous messages are written.
+    W: Unpin + AsyncWrite
+}
+
+impl<IO> WebSocket<IO>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    impl_close! {
+        async;
+        /// There is no timer in `futures-io`, So use the timeout of your runtime to limit the wait.
+    }
+
+    #[inline]
+    async fn wait_for_close(&mut self) -> Result<()> {
+        self.recv_close().await
+    }
+
+    #[inline]
+    async fn shutdown(&mut self) -> Result<()> {
+        poll_fn(|cx| self.poll_shutdown(cx)).await
+    }
+}
+
+// ------------------------------------------------------------------------
+
+/// Read the upgrade request from `stream`, reply to it and returns a websocket server instance.
+///
+/// Same as [handshake::accept], See it for more details.
+pub async fn accept<IO>(stream: IO) -> std::result::Result<WebSocket<IO>, Error>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    accept_with(stream, &handshake::Config::default()).await
+}
+
+/// Same as [accept], But negotiate handshake parameters using `config`.
+pub async fn accept_with<IO>(
+    mut stream: IO,
+    config: &handshake::Config,
+) -> std::result::Result<WebSocket<IO>, Error>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    let head = read_head(&mut stream).await;
+    let req = head.and_then(|((line, headers), buffered)| {
+        Ok((Request::from_head(&line, headers)?, buffered))
+    });
+    let result = req.and_then(|(req, buffered)| Ok((config.respond(&req)?, buffered)));
+    match result {
+        Ok(((negotiated, res), buffered)) => {
+            write_all(&mut stream, res.as_bytes()).await?;
+            poll_fn(|cx| Pin::new(&mut stream).poll_flush(cx)).await?;
+            Ok(WebSocket::from_parts(stream, Role::Server, buffered).with_negotiated(negotiated))
+        }
+        Err(err) => {
+            if let Some(res) = err.rejection() {
+                write_all(&mut stream, res.as_bytes()).await?;
+                poll_fn(|cx| Pin::new(&mut stream).poll_flush(cx)).await?;
+            }
+            Err(err)
+        }
+    }
+}
+
+/// Perform client handshake over an established `stream`, Returns a websocket client instance.
+///
+/// Same as [client::handshake], See it for more details.
+pub async fn handshake<IO>(
+    stream: IO,
+    host: &str,
+    path: &str,
+) -> std::result::Result<WebSocket<IO>, Error>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    handshake_with(stream, host, path, &client::Config::default()).await
+}
+
+/// Same as [handshake()], But with additional options.
+pub async fn handshake_with<IO>(
+    mut stream: IO,
+    host: &str,
+    path: &str,
+    config: &client::Config,
+) -> std::result::Result<WebSocket<IO>, Error>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    let (req, sec_key) = config.request(host, path);
+    write_all(&mut stream, req.as_bytes()).await?;
+    poll_fn(|cx| Pin::new(&mut stream).poll_flush(cx)).await?;
+
+    let ((line, headers), buffered) = read_head(&mut stream).await.map_err(bad_response)?;
+    let mut res = Response::from_head(&line, headers)?;
+    if res.status != 101 {
+        res.body = read_body(&mut stream, &res, buffered).await?;
+        return Err(Error::Rejected(res));
+    }
+    let negotiated = res.validate(&sec_key, config)?;
+    Ok(WebSocket::from_parts(stream, Role::Client, buffered).with_negotiated(negotiated))
+}
+
+/// Read http head, Returns it along with the bytes that were read past the head.
+async fn read_head<R>(reader: &mut R) -> std::result::Result<(Head, Vec<u8>), Error>
+where
+    R: Unpin + AsyncRead,
+{
+    let mut buf = vec![];
+    loop {
+        if read_some(reader, &mut buf).await? == 0 {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "incomplete http head").into());
+        }
+        if let Some((head, head_len)) = parse_head(&buf)? {
+            buf.drain(..head_len);
+            return Ok((head, buf));
+        }
+    }
+}
+
+/// Read response body, if `Content-Length` header is present. `buffered` bytes are the beginning of the body.
+async fn read_body<R>(
+    reader: &mut R,
+    res: &Response,
+    mut body: Vec<u8>,
+) -> std::result::Result<Vec<u8>, Error>
+where
+    R: Unpin + AsyncRead,
+{
+    let Some(len) = res.header("content-length") else {
+        return Ok(vec![]);
+    };
+    let len = len
+        .parse::<u64>()
+        .map_err(|_| Error::BadResponse("invalid content length"))?;
+    let len = len.min(handshake::MAX_HEAD_LEN) as usize;
+    while body.len() < len {
+        if read_some(reader, &mut body).await? == 0 {
+            break;
+        }
+    }
+    body.truncate(len);
+    Ok(body)
+}
+
+/// Read some bytes from `reader`, And append them to `buf`.
+async fn read_some<R>(reader: &mut R, buf: &mut Vec<u8>) -> Result<usize>
+where
+    R: Unpin + AsyncRead,
+{
+    let len = buf.len();
+    buf.resize(len + 4096, 0);
+    let result = poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, &mut buf[len..])).await;
+    buf.truncate(len + *result.as_ref().unwrap_or(&0));
+    result
+}
+
+async fn write_all<W>(writer: &mut W, mut buf: &[u8]) -> Result<()>
+where
+    W: Unpin + AsyncWrite,
+{
+    while !buf.is_empty() {
+        match poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, buf)).await {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(amt) => buf = &buf[amt..],
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,804 @@
+//! # WebSocket opening handshake
+//!
+//! A client sends a handshake request to the server. It includes the following information:
+//!
+//! ```yml
+//! GET /chat HTTP/1.1
+//! Host: example.com:8000
+//! Upgrade: websocket
+//! Connection: Upgrade
+//! Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==
+//! Sec-WebSocket-Version: 13
+//! ```
+//!
+//! The server must be careful to understand everything the client asks for, otherwise security issues can occur.
+//! If any header is not understood or has an incorrect value, the server should send a 400 ("Bad Request") response and immediately close the socket.
+//!
+//! ### Tips
+//!
+//! All browsers send an Origin header.
+//! You can use this header for security (checking for same origin, automatically allowing or denying, etc.) and send a 403 Forbidden if you don't like what you see.
+//! However, be warned that non-browser agents can send a faked Origin.
+//!
+//! ### Note
+//!
+//! -  HTTP version must be `1.1` or greater, and method must be `GET`
+//! - `Upgrade` header field containing the value `"websocket"`
+//! - `Connection` header field that includes the token `"Upgrade"`
+//! - `Sec-WebSocket-Version` header field containing the value `13`, Otherwise the server responds with `426 Upgrade Required`.
+//! - `Sec-WebSocket-Key` header field with a base64-encoded value that, when decoded, is 16 bytes in length.
+//! -  Request may include any other header fields, for example, cookies and/or authentication-related header fields.
+//!
+//! ### Example
+//!
+//! ```no_run
+//! # #[cfg(feature = "tokio")]
+//! # async fn run() -> std::io::Result<()> {
+//! use tokio::net::TcpListener;
+//! use web_socket::handshake;
+//!
+//! let listener = TcpListener::bind("127.0.0.1:8080").await?;
+//! loop {
+//!     let (stream, _) = listener.accept().await?;
+//!     let mut ws = handshake::accept(stream).await?;
+//!     ws.send("Hello!").await?;
+//! }
+//! # }
+//! ```
+
+use crate::io;
+use crate::{
+    client,
+    extension::{rsv_bits, Extension, Negotiator, Offer, ParseError},
+};
+#[cfg(feature = "tokio")]
+use crate::{Role, WebSocket};
+use alloc::{boxed::Box, format, string::String, sync::Arc, vec, vec::Vec};
+use core::fmt;
+use sha1::{Digest, Sha1};
+#[cfg(feature = "tokio")]
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+    Take,
+};
+
+/// WebSocket magic string used during the WebSocket handshake
+pub const MAGIC_STRING: &[u8; 36] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Maximum size of the http head (request line and headers) in bytes.
+pub const MAX_HEAD_LEN: u64 = 16 * 1024;
+
+/// Create `Sec-WebSocket-Accept` key from `Sec-WebSocket-Key` http header value.
+///
+/// ### Example
+///
+/// ```rust
+/// use web_socket::handshake::accept_key_from;
+/// assert_eq!(accept_key_from("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+/// ```
+#[inline]
+pub fn accept_key_from(sec_ws_key: impl AsRef<[u8]>) -> String {
+    let mut sha1 = Sha1::new();
+    sha1.update(sec_ws_key.as_ref());
+    sha1.update(MAGIC_STRING);
+    base64_encode(sha1.finalize())
+}
+
+/// Reasons why a websocket handshake failed.
+#[derive(Debug)]
+pub enum Error {
+    /// An I/O error occurred while reading or writing the http message.
+    Io(io::Error),
+    /// The request is not a valid websocket upgrade request.
+    ///
+    /// The server responds with `400 Bad Request`.
+    BadRequest(&'static str),
+    /// `Sec-WebSocket-Version` header is missing or it isn't `13`.
+    ///
+    /// The server responds with `426 Upgrade Required`.
+    UnsupportedVersion,
+    /// The url is not a valid websocket url.
+    InvalidUrl(&'static str),
+    /// The server response is not a valid websocket upgrade response.
+    BadResponse(&'static str),
+    /// The server responded with a status code other than `101 Switching Protocols`.
+    Rejected(Response),
+}
+
+impl Error {
+    /// Returns the http response that rejects the handshake, if any.
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// use web_socket::handshake::Error;
+    /// let res = Error::UnsupportedVersion.rejection().unwrap();
+    /// assert!(res.starts_with("HTTP/1.1 426 Upgrade Required\r\n"));
+    /// assert!(res.contains("Sec-WebSocket-Version: 13\r\n"));
+    /// ```
+    pub fn rejection(&self) -> Option<String> {
+        let (status, extra) = match self {
+            Error::BadRequest(_) => ("400 Bad Request", ""),
+            Error::UnsupportedVersion => ("426 Upgrade Required", "Sec-WebSocket-Version: 13\r\n"),
+            _ => return None,
+        };
+        Some(format!(
+            "HTTP/1.1 {status}\r\nConnection: close\r\nContent-Length: 0\r\n{extra}\r\n"
+        ))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => err.fmt(f),
+            Error::BadRequest(msg) => write!(f, "bad request: {msg}"),
+            Error::UnsupportedVersion => f.write_str("unsupported websocket version"),
+            Error::InvalidUrl(msg) => write!(f, "invalid url: {msg}"),
+            Error::BadResponse(msg) => write!(f, "bad response: {msg}"),
+            Error::Rejected(res) => write!(f, "handshake rejected: {} {}", res.status, res.reason),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+/// Http request line and header fields.
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// Request method, For example: `GET`
+    pub method: String,
+    /// Request target, For example: `/chat?room=1`
+    pub path: String,
+    /// Http version as `(major, minor)`, For example: `(1, 1)`
+    pub version: (u8, u8),
+    /// Header fields in the order they were received.
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Read http request head (request line and header fields) from `reader`.
+    ///
+    /// Reading stops right after the empty line that terminates the head,
+    /// So the request body (if any) is left unread in `reader`.
+    #[cfg(feature = "tokio")]
+    pub async fn parse<R>(reader: &mut R) -> Result<Self, Error>
+    where
+        R: Unpin + AsyncBufRead,
+    {
+        let (line, headers) = read_head(reader).await?;
+        Self::from_head(&line, headers)
+    }
+
+    /// Parse request line, Along with the header fields.
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    pub(crate) fn from_head(line: &str, headers: Vec<(String, String)>) -> Result<Self, Error> {
+        let mut parts = line.split(' ');
+        let (Some(method), Some(path), Some(version), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(Error::BadRequest("invalid request line"));
+        };
+        Ok(Request {
+            method: method.into(),
+            path: path.into(),
+            version: parse_version(version).ok_or(Error::BadRequest("invalid http version"))?,
+            headers,
+        })
+    }
+
+    /// Returns the value of the first header field named `name`. (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Returns `true` if any comma separated value of the header fields named `name` is equal to `token`. (case-insensitive)
+    pub fn has_token(&self, name: &str, token: &str) -> bool {
+        has_token(&self.headers, name, token)
+    }
+
+    /// Returns subprotocols offered by the client with `Sec-WebSocket-Protocol` header, in order of preference.
+    pub fn protocols(&self) -> Vec<&str> {
+        header_values(&self.headers, "sec-websocket-protocol").collect()
+    }
+
+    /// Returns extensions offered by the client with `Sec-WebSocket-Extensions` header, in order of preference.
+    pub fn extensions(&self) -> Result<Vec<Offer>, Error> {
+        parse_extensions(&self.headers)
+            .map_err(|_| Error::BadRequest("invalid `Sec-WebSocket-Extensions` header"))
+    }
+
+    /// Validate websocket upgrade request and returns `Sec-WebSocket-Key` header value.
+    pub fn sec_key(&self) -> Result<&str, Error> {
+        if self.method != "GET" {
+            return Err(Error::BadRequest("method must be `GET`"));
+        }
+        if self.version < (1, 1) {
+            return Err(Error::BadRequest("http version must be `1.1` or greater"));
+        }
+        if !self.has_token("upgrade", "websocket") {
+            return Err(Error::BadRequest("missing `Upgrade: websocket` header"));
+        }
+        if !self.has_token("connection", "upgrade") {
+            return Err(Error::BadRequest("missing `Connection: Upgrade` header"));
+        }
+        if self.header("sec-websocket-version").map(str::trim) != Some("13") {
+            return Err(Error::UnsupportedVersion);
+        }
+        let key = self
+            .header("sec-websocket-key")
+            .ok_or(Error::BadRequest("missing `Sec-WebSocket-Key` header"))?
+            .trim();
+
+        match base64::Engine::decode(&base64::prelude::BASE64_STANDARD, key) {
+            Ok(nonce) if nonce.len() == 16 => Ok(key),
+            _ => Err(Error::BadRequest("invalid `Sec-WebSocket-Key` header")),
+        }
+    }
+}
+
+/// Http status line, header fields and body.
+#[derive(Debug, Clone)]
+pub struct Response {
+    /// Http version as `(major, minor)`, For example: `(1, 1)`
+    pub version: (u8, u8),
+    /// Status code, For example: `101`
+    pub status: u16,
+    /// Reason phrase, For example: `Switching Protocols`
+    pub reason: String,
+    /// Header fields in the order they were received.
+    pub headers: Vec<(String, String)>,
+    /// Response body.
+    ///
+    /// It is only read if `Content-Length` header is present, and at most [MAX_HEAD_LEN] bytes.
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// Read http response head (status line and header fields) from `reader`.
+    ///
+    /// The body is not read, Because the connection is upgraded on `101 Switching Protocols`.
+    #[cfg(feature = "tokio")]
+    pub async fn parse<R>(reader: &mut R) -> Result<Self, Error>
+    where
+        R: Unpin + AsyncBufRead,
+    {
+        let (line, headers) = read_head(reader).await.map_err(bad_response)?;
+        Self::from_head(&line, headers)
+    }
+
+    /// Parse status line, Along with the header fields.
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    pub(crate) fn from_head(line: &str, headers: Vec<(String, String)>) -> Result<Self, Error> {
+        let mut parts = line.splitn(3, ' ');
+        let (Some(version), Some(status), reason) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(Error::BadResponse("invalid status line"));
+        };
+        Ok(Response {
+            version: parse_version(version).ok_or(Error::BadResponse("invalid http version"))?,
+            status: match status.parse() {
+                Ok(status @ 100..=999) => status,
+                _ => return Err(Error::BadResponse("invalid status code")),
+            },
+            reason: reason.unwrap_or_default().into(),
+            headers,
+            body: vec![],
+        })
+    }
+
+    /// Read response body from `reader`, if `Content-Length` header is present.
+    #[cfg(feature = "tokio")]
+    pub async fn read_body<R>(&mut self, reader: &mut R) -> Result<(), Error>
+    where
+        R: Unpin + AsyncBufRead,
+    {
+        if let Some(len) = self.header("content-length") {
+            let len = len
+                .parse::<u64>()
+                .map_err(|_| Error::BadResponse("invalid content length"))?;
+            self.body.clear();
+            reader
+                .take(len.min(MAX_HEAD_LEN))
+                .read_to_end(&mut self.body)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the value of the first header field named `name`. (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Returns `true` if any comma separated value of the header fields named `name` is equal to `token`. (case-insensitive)
+    pub fn has_token(&self, name: &str, token: &str) -> bool {
+        has_token(&self.headers, name, token)
+    }
+
+    /// Returns extensions accepted by the server with `Sec-WebSocket-Extensions` header, in the order they should be applied.
+    pub fn extensions(&self) -> Result<Vec<Offer>, Error> {
+        parse_extensions(&self.headers)
+            .map_err(|_| Error::BadResponse("invalid `Sec-WebSocket-Extensions` header"))
+    }
+
+    /// Validate websocket upgrade response against `Sec-WebSocket-Key`, subprotocols and extensions that were sent with the request.
+    pub fn validate(&self, sec_key: &str, config: &client::Config) -> Result<Negotiated, Error> {
+        if !self.has_token("upgrade", "websocket") {
+            return Err(Error::BadResponse("missing `Upgrade: websocket` header"));
+        }
+        if !self.has_token("connection", "upgrade") {
+            return Err(Error::BadResponse("missing `Connection: Upgrade` header"));
+        }
+        if self.header("sec-websocket-accept") != Some(&accept_key_from(sec_key)) {
+            return Err(Error::BadResponse("invalid `Sec-WebSocket-Accept` header"));
+        }
+        let protocol = match self.header("sec-websocket-protocol").map(str::trim) {
+            Some(protocol) if config.protocols.iter().any(|p| p == protocol) => {
+                Some(protocol.into())
+            }
+            Some(_) => {
+                return Err(Error::BadResponse(
+                    "server selected a subprotocol that was not offered",
+                ))
+            }
+            None => None,
+        };
+        let mut extensions: Vec<Box<dyn Extension>> = vec![];
+        for response in self.extensions()? {
+            let negotiator = config
+                .extensions
+                .iter()
+                .find(|n| n.name().eq_ignore_ascii_case(&response.name))
+                .ok_or(Error::BadResponse(
+                    "server accepted an extension that was not offered",
+                ))?;
+
+            if extensions.iter().any(|ext| ext.name() == negotiator.name()) {
+                return Err(Error::BadResponse(
+                    "server accepted an extension more than once",
+                ));
+            }
+            let extension = negotiator.confirm(&response).map_err(Error::BadResponse)?;
+            if extension.rsv() & rsv_bits(&extensions) != 0 {
+                return Err(Error::BadResponse("extensions use the same reserved bits"));
+            }
+            extensions.push(extension);
+        }
+        Ok(Negotiated {
+            protocol,
+            extensions,
+        })
+    }
+}
+
+/// ## Server handshake response
+///
+/// When the server receives the handshake request,
+/// It should send back a special response that indicates that the protocol will be changing from HTTP to WebSocket.
+///
+/// The `Sec-WebSocket-Accept` header is important in that the server must derive it from the `Sec-WebSocket-Key` that the client sent to it.
+///
+/// ### Example
+///
+/// ```rust
+/// let res = [
+///     "HTTP/1.1 101 Switching Protocols",
+///     "Upgrade: websocket",
+///     "Connection: Upgrade",
+///     "Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=",
+///     "",
+///     ""
+/// ];
+/// let field: Option<(&str, &str)> = None;
+/// assert_eq!(web_socket::handshake::response("dGhlIHNhbXBsZSBub25jZQ==", field), res.join("\r\n"));
+/// ```
+///
+/// To get it, concatenate the client's `Sec-WebSocket-Key` and the string _"258EAFA5-E914-47DA-95CA-C5AB0DC85B11"_ together (it's a [Magic string](https://en.wikipedia.org/wiki/Magic_string)), take the SHA-1 hash of the result, and return the base64 encoding of that hash.
+///
+/// ### Note
+///
+/// - Regular HTTP status codes can be used only before the handshake. After the handshake succeeds, you have to use a different set of codes (defined in section 7.4 of the spec)
+pub fn response(
+    sec_ws_key: impl AsRef<[u8]>,
+    headers: impl IntoIterator<Item = impl Header>,
+) -> String {
+    let key = accept_key_from(sec_ws_key);
+    let headers: String = headers.into_iter().map(|f| Header::fmt(&f)).collect();
+    format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {key}\r\n{headers}\r\n")
+}
+
+/// Callback that selects a subprotocol from the ones offered by the client.
+pub type SelectProtocol = Box<dyn Fn(&[&str]) -> Option<String> + Send + Sync>;
+
+/// Server handshake options.
+///
+/// ### Example
+///
+/// ```rust
+/// use web_socket::handshake::Config;
+///
+/// let config = Config {
+///     protocols: vec!["graphql-ws".into(), "json".into()],
+///     ..Default::default()
+/// };
+///
+/// // Or select subprotocol with a callback.
+/// let config = Config {
+///     select_protocol: Some(Box::new(|offered| {
+///         offered.iter().find(|p| p.starts_with("v2.")).map(|p| p.to_string())
+///     })),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Default)]
+pub struct Config {
+    /// Supported subprotocols, in order of preference.
+    ///
+    /// The first one that is also offered by the client is selected.
+    pub protocols: Vec<String>,
+    /// Select a subprotocol from the ones offered by the client (in client's order of preference).
+    ///
+    /// If set, It is used instead of [Config::protocols]. Returned value must be one of the offered subprotocols, otherwise it is ignored.
+    pub select_protocol: Option<SelectProtocol>,
+    /// Supported extensions, in the order they are applied.
+    ///
+    /// Each extension accepts at most one of the client's offers.
+    pub extensions: Vec<Arc<dyn Negotiator>>,
+}
+
+impl Config {
+    /// Validate the upgrade request and negotiate handshake parameters, Returns them along with the `101 Switching Protocols` response.
+    ///
+    /// On error, [Error::rejection] is the response to be sent.
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    pub(crate) fn respond(&self, req: &Request) -> Result<(Negotiated, String), Error> {
+        let key = req.sec_key()?;
+        let (negotiated, extensions) = self.negotiate(req)?;
+        let protocol = negotiated
+            .protocol
+            .clone()
+            .map(|protocol| ("Sec-WebSocket-Protocol", protocol));
+
+        let extensions = Some(("Sec-WebSocket-Extensions", Offer::to_header(&extensions)))
+            .filter(|_| !extensions.is_empty());
+
+        let headers = protocol.into_iter().chain(extensions);
+        Ok((negotiated, response(key, headers)))
+    }
+
+    /// Negotiate handshake parameters for `req`, Returns them along with accepted extensions, That are sent back to the client.
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    fn negotiate(&self, req: &Request) -> Result<(Negotiated, Vec<Offer>), Error> {
+        let offered = req.protocols();
+        let protocol = match &self.select_protocol {
+            Some(select) => select(&offered).filter(|p| offered.contains(&p.as_str())),
+            None => self
+                .protocols
+                .iter()
+                .find(|p| offered.contains(&p.as_str()))
+                .cloned(),
+        };
+
+        let offers = req.extensions()?;
+        let mut responses = vec![];
+        let mut extensions: Vec<Box<dyn Extension>> = vec![];
+        for negotiator in &self.extensions {
+            let offers: Vec<Offer> = offers
+                .iter()
+                .filter(|offer| offer.name.eq_ignore_ascii_case(negotiator.name()))
+                .cloned()
+                .collect();
+
+            if offers.is_empty() {
+                continue;
+            }
+            if let Some((response, extension)) = negotiator.accept(&offers) {
+                if extension.rsv() & rsv_bits(&extensions) == 0 {
+                    responses.push(response);
+                    extensions.push(extension);
+                }
+            }
+        }
+        let negotiated = Negotiated {
+            protocol,
+            extensions,
+        };
+        Ok((negotiated, responses))
+    }
+}
+
+/// Handshake parameters agreed by both endpoints.
+#[derive(Debug, Default)]
+pub struct Negotiated {
+    /// Selected subprotocol, from `Sec-WebSocket-Protocol` header.
+    pub protocol: Option<String>,
+    /// Negotiated extensions, in the order they are applied.
+    pub extensions: Vec<Box<dyn Extension>>,
+}
+
+/// Validate the upgrade request and write the handshake response to `writer`.
+///
+/// On success, `101 Switching Protocols` is written, Otherwise `400 Bad Request` or `426 Upgrade Required` is written and the error is returned.
+#[cfg(feature = "tokio")]
+pub async fn reply<W>(writer: &mut W, req: &Request) -> Result<(), Error>
+where
+    W: Unpin + AsyncWrite,
+{
+    reply_with(writer, req, &Config::default()).await?;
+    Ok(())
+}
+
+/// Same as [reply], But negotiate handshake parameters using `config`.
+///
+/// ### Example
+///
+/// ```no_run
+/// use tokio::io::BufReader;
+/// use web_socket::{handshake::{self, Config, Request}, Role, WebSocket};
+///
+/// # async fn run(stream: tokio::net::TcpStream, config: Config) -> Result<(), handshake::Error> {
+/// let (reader, mut writer) = stream.into_split();
+/// let mut reader = BufReader::new(reader);
+/// let req = Request::parse(&mut reader).await?;
+/// let negotiated = handshake::reply_with(&mut writer, &req, &config).await?;
+///
+/// let buffered = reader.buffer().to_vec();
+/// let ws = WebSocket::from_parts(reader.into_inner(), Role::Server, buffered).with_negotiated(negotiated);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "tokio")]
+pub async fn reply_with<W>(
+    writer: &mut W,
+    req: &Request,
+    config: &Config,
+) -> Result<Negotiated, Error>
+where
+    W: Unpin + AsyncWrite,
+{
+    match config.respond(req) {
+        Ok((negotiated, res)) => {
+            writer.write_all(res.as_bytes()).await?;
+            writer.flush().await?;
+            Ok(negotiated)
+        }
+        Err(err) => reject(writer, err).await,
+    }
+}
+
+#[cfg(feature = "tokio")]
+async fn reject<W, T>(writer: &mut W, err: Error) -> Result<T, Error>
+where
+    W: Unpin + AsyncWrite,
+{
+    if let Some(res) = err.rejection() {
+        writer.write_all(res.as_bytes()).await?;
+        writer.flush().await?;
+    }
+    Err(err)
+}
+
+/// Read the upgrade request from `stream`, reply to it and returns a websocket server instance.
+///
+/// If the request is not a valid websocket upgrade request, The rejection response is written and the error is returned.
+///
+/// Any bytes that were read past the request head (for example, websocket frames sent right after the request)
+/// are not lost, The returned websocket reads them before reading from `stream`.
+#[cfg(feature = "tokio")]
+pub async fn accept<IO>(stream: IO) -> Result<WebSocket<IO>, Error>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    accept_with(stream, &Config::default()).await
+}
+
+/// Same as [accept], But negotiate handshake parameters using `config`.
+#[cfg(feature = "tokio")]
+pub async fn accept_with<IO>(mut stream: IO, config: &Config) -> Result<WebSocket<IO>, Error>
+where
+    IO: Unpin + AsyncRead + AsyncWrite,
+{
+    let mut reader = BufReader::new(&mut stream);
+    let req = Request::parse(&mut reader).await;
+    let buffered = reader.buffer().to_vec();
+    let req = match req {
+        Ok(req) => req,
+        Err(err) => return reject(&mut stream, err).await,
+    };
+    let negotiated = reply_with(&mut stream, &req, config).await?;
+    Ok(WebSocket::from_parts(stream, Role::Server, buffered).with_negotiated(negotiated))
+}
+
+/// Provides a interface for formatting HTTP headers
+///
+/// # Example
+///
+/// ```rust
+/// use web_socket::handshake::Header;
+///
+/// assert_eq!(Header::fmt(&("val", 2)), "val: 2\r\n");
+/// assert_eq!(Header::fmt(&["key", "value"]), "key: value\r\n");
+/// ```
+pub trait Header {
+    /// Format a single http header field
+    fn fmt(_: &Self) -> String;
+}
+
+impl<T: Header> Header for &T {
+    fn fmt(this: &Self) -> String {
+        T::fmt(this)
+    }
+}
+impl<T: fmt::Display> Header for [T; 2] {
+    fn fmt([key, value]: &Self) -> String {
+        format!("{key}: {value}\r\n")
+    }
+}
+impl<K: fmt::Display, V: fmt::Display> Header for (K, V) {
+    fn fmt((key, value): &Self) -> String {
+        format!("{key}: {value}\r\n")
+    }
+}
+
+// ------------------------------------------------------------------------
+
+/// Start line and header fields of http head.
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+pub(crate) type Head = (String, Vec<(String, String)>);
+
+/// Read start line and header fields.
+#[cfg(feature = "tokio")]
+async fn read_head<R>(reader: &mut R) -> Result<Head, Error>
+where
+    R: Unpin + AsyncBufRead,
+{
+    let mut reader = reader.take(MAX_HEAD_LEN);
+    let mut start = String::new();
+    read_line(&mut reader, &mut start).await?;
+
+    let mut headers = vec![];
+    let mut line = String::new();
+    loop {
+        read_line(&mut reader, &mut line).await?;
+        if line.is_empty() {
+            break Ok((start, headers));
+        }
+        headers.push(parse_header(&line)?);
+    }
+}
+
+/// Parse start line and header fields from `buf`, Returns them along with the length of the head.
+///
+/// Returns `None`, If the head is incomplete.
+#[cfg(feature = "futures-io")]
+pub(crate) fn parse_head(buf: &[u8]) -> Result<Option<(Head, usize)>, Error> {
+    let mut start = None;
+    let mut headers = vec![];
+    let mut pos = 0;
+    while let Some(end) = buf[pos..].iter().position(|&b| b == b'\n') {
+        let line = &buf[pos..pos + end];
+        pos += end + 1;
+        if pos as u64 > MAX_HEAD_LEN {
+            return Err(Error::BadRequest("http head too large"));
+        }
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let line = core::str::from_utf8(line)
+            .map_err(|_| Error::BadRequest("invalid utf-8 in http head"))?;
+        match start {
+            None => start = Some(line.to_owned()),
+            Some(start) if line.is_empty() => return Ok(Some(((start, headers), pos))),
+            Some(_) => headers.push(parse_header(line)?),
+        }
+    }
+    if buf.len() as u64 >= MAX_HEAD_LEN {
+        return Err(Error::BadRequest("http head too large"));
+    }
+    Ok(None)
+}
+
+/// Errors of reading the response head are reported as [Error::BadResponse].
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+pub(crate) fn bad_response(err: Error) -> Error {
+    match err {
+        Error::BadRequest(msg) => Error::BadResponse(msg),
+        err => err,
+    }
+}
+
+fn parse_extensions(headers: &[(String, String)]) -> Result<Vec<Offer>, ParseError> {
+    let mut extensions = vec![];
+    for (_, value) in headers
+        .iter()
+        .filter(|(key, _)| key.eq_ignore_ascii_case("sec-websocket-extensions"))
+    {
+        extensions.extend(Offer::parse(value)?);
+    }
+    Ok(extensions)
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn header_values<'a>(
+    headers: &'a [(String, String)],
+    name: &'a str,
+) -> impl Iterator<Item = &'a str> + 'a {
+    headers
+        .iter()
+        .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+        .flat_map(|(_, value)| value.split(','))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn has_token(headers: &[(String, String)], name: &str, token: &str) -> bool {
+    header_values(headers, name).any(|value| value.eq_ignore_ascii_case(token))
+}
+
+/// Read a single line (without `CRLF`) into `line`.
+#[cfg(feature = "tokio")]
+async fn read_line<R>(reader: &mut Take<&mut R>, line: &mut String) -> Result<(), Error>
+where
+    R: Unpin + AsyncBufRead,
+{
+    let mut buf = core::mem::take(line).into_bytes();
+    buf.clear();
+    reader.read_until(b'\n', &mut buf).await?;
+    match buf.strip_suffix(b"\n") {
+        Some(rest) => buf.truncate(rest.strip_suffix(b"\r").unwrap_or(rest).len()),
+        None if reader.limit() == 0 => return Err(Error::BadRequest("http head too large")),
+        None => {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "incomplete http head").into())
+        }
+    }
+    *line = String::from_utf8(buf).map_err(|_| Error::BadRequest("invalid utf-8 in http head"))?;
+    Ok(())
+}
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+fn parse_version(version: &str) -> Option<(u8, u8)> {
+    let (major, minor) = version.strip_prefix("HTTP/")?.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+fn parse_header(line: &str) -> Result<(String, String), Error> {
+    match line.split_once(':') {
+        Some((key, value)) if !key.is_empty() && !key.contains(char::is_whitespace) => {
+            Ok((key.into(), value.trim().into()))
+        }
+        _ => Err(Error::BadRequest("invalid header field")),
+    }
+}
+
+pub(crate) fn base64_encode(string: impl AsRef<[u8]>) -> String {
+    base64::Engine::encode(&base64::prelude::BASE64_STANDARD, string)
+}
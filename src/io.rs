@@ -0,0 +1,175 @@
+//! I/O error types, That are used by this crate.
+//!
+//! With `std` feature these are re-exported from [std::io], Otherwise they are a minimal replacement for `no_std` targets.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Result};
+
+#[cfg(not(feature = "std"))]
+pub use no_std::{Error, ErrorKind, Result};
+
+#[cfg(not(feature = "std"))]
+mod no_std {
+    use alloc::boxed::Box;
+    use core::fmt;
+
+    /// A specialized [core::result::Result] type for I/O operations.
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// The kind of an I/O error, Same as `std::io::ErrorKind`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[non_exhaustive]
+    pub enum ErrorKind {
+        /// An entity was not found.
+        NotFound,
+        /// The operation lacked the necessary privileges to complete.
+        PermissionDenied,
+        /// The connection was refused by the remote server.
+        ConnectionRefused,
+        /// The connection was reset by the remote server.
+        ConnectionReset,
+        /// The connection was aborted (terminated) by the remote server.
+        ConnectionAborted,
+        /// The network operation failed because it was not connected yet.
+        NotConnected,
+        /// A socket address could not be bound because the address is already in use elsewhere.
+        AddrInUse,
+        /// A nonexistent interface was requested or the requested address was not local.
+        AddrNotAvailable,
+        /// The operation failed because a pipe was closed.
+        BrokenPipe,
+        /// An entity already exists.
+        AlreadyExists,
+        /// The operation needs to block to complete, but the blocking operation was requested to not occur.
+        WouldBlock,
+        /// A parameter was incorrect.
+        InvalidInput,
+        /// Data not valid for the operation were encountered.
+        InvalidData,
+        /// The I/O operation's timeout expired, causing it to be canceled.
+        TimedOut,
+        /// An error returned when an operation could not be completed because a call to `write` returned `Ok(0)`.
+        WriteZero,
+        /// This operation was interrupted.
+        Interrupted,
+        /// This operation is unsupported on this platform.
+        Unsupported,
+        /// An error returned when an operation could not be completed because an "end of file" was reached prematurely.
+        UnexpectedEof,
+        /// An operation could not be completed, because it failed to allocate enough memory.
+        OutOfMemory,
+        /// A custom error that does not fall under any other I/O error kind.
+        Other,
+    }
+
+    impl ErrorKind {
+        fn as_str(&self) -> &'static str {
+            match self {
+                ErrorKind::NotFound => "entity not found",
+                ErrorKind::PermissionDenied => "permission denied",
+                ErrorKind::ConnectionRefused => "connection refused",
+                ErrorKind::ConnectionReset => "connection reset",
+                ErrorKind::ConnectionAborted => "connection aborted",
+                ErrorKind::NotConnected => "not connected",
+                ErrorKind::AddrInUse => "address in use",
+                ErrorKind::AddrNotAvailable => "address not available",
+                ErrorKind::BrokenPipe => "broken pipe",
+                ErrorKind::AlreadyExists => "entity already exists",
+                ErrorKind::WouldBlock => "operation would block",
+                ErrorKind::InvalidInput => "invalid input parameter",
+                ErrorKind::InvalidData => "invalid data",
+                ErrorKind::TimedOut => "timed out",
+                ErrorKind::WriteZero => "write zero",
+                ErrorKind::Interrupted => "operation interrupted",
+                ErrorKind::Unsupported => "unsupported",
+                ErrorKind::UnexpectedEof => "unexpected end of file",
+                ErrorKind::OutOfMemory => "out of memory",
+                ErrorKind::Other => "other error",
+            }
+        }
+    }
+
+    impl fmt::Display for ErrorKind {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.as_str())
+        }
+    }
+
+    /// The error type for I/O operations, Same as `std::io::Error`.
+    pub struct Error {
+        kind: ErrorKind,
+        error: Option<Box<dyn core::error::Error + Send + Sync>>,
+    }
+
+    impl Error {
+        /// Creates a new I/O error from a known kind of error as well as an arbitrary error payload.
+        pub fn new<E>(kind: ErrorKind, error: E) -> Self
+        where
+            E: Into<Box<dyn core::error::Error + Send + Sync>>,
+        {
+            Self {
+                kind,
+                error: Some(error.into()),
+            }
+        }
+
+        /// Creates a new I/O error of [ErrorKind::Other] kind, From an arbitrary error payload.
+        pub fn other<E>(error: E) -> Self
+        where
+            E: Into<Box<dyn core::error::Error + Send + Sync>>,
+        {
+            Self::new(ErrorKind::Other, error)
+        }
+
+        /// Returns the corresponding [ErrorKind] for this error.
+        #[inline]
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+
+        /// Returns a reference to the inner error, If any.
+        pub fn get_ref(&self) -> Option<&(dyn core::error::Error + Send + Sync + 'static)> {
+            self.error.as_deref()
+        }
+
+        /// Consumes the error, Returning its inner error (if any).
+        pub fn into_inner(self) -> Option<Box<dyn core::error::Error + Send + Sync>> {
+            self.error
+        }
+    }
+
+    impl From<ErrorKind> for Error {
+        #[inline]
+        fn from(kind: ErrorKind) -> Self {
+            Self { kind, error: None }
+        }
+    }
+
+    impl fmt::Debug for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match &self.error {
+                Some(error) => f
+                    .debug_struct("Custom")
+                    .field("kind", &self.kind)
+                    .field("error", error)
+                    .finish(),
+                None => f.debug_tuple("Kind").field(&self.kind).finish(),
+            }
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match &self.error {
+                Some(error) => error.fmt(f),
+                None => self.kind.fmt(f),
+            }
+        }
+    }
+
+    impl core::error::Error for Error {
+        fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+            self.error.as_ref().and_then(|error| error.source())
+        }
+    }
+}
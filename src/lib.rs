@@ -1,15 +1,82 @@
 #![doc(html_logo_url = "https://cdn.worldvectorlogo.com/logos/websocket.svg")]
-#![doc = include_str!("../README.md")]
+// Examples of the README are driven by `tokio`.
+#![cfg_attr(feature = "tokio", doc = include_str!("../README.md"))]
+#![cfg_attr(
+    not(feature = "tokio"),
+    doc = "Fastest webSocket implementation for both client and server."
+)]
 #![warn(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+mod budget;
+#[cfg(feature = "alloc")]
+mod connection;
+mod error;
 mod frame;
+#[cfg(feature = "alloc")]
+mod prepared;
+#[cfg(feature = "alloc")]
+mod utf8;
+#[cfg(any(feature = "std", feature = "embedded-io-async"))]
+#[macro_use]
+mod wrapper;
+#[cfg(feature = "tokio")]
 mod ws;
+
+#[cfg(feature = "std")]
+pub mod blocking;
+#[cfg(feature = "alloc")]
+pub mod client;
+#[cfg(feature = "tokio-util")]
+pub mod codec;
+#[cfg(feature = "deflate")]
+pub mod deflate;
+#[cfg(feature = "embedded-io-async")]
+pub mod embedded;
+#[cfg(feature = "alloc")]
+pub mod extension;
+#[cfg(feature = "futures-io")]
+pub mod futures;
+#[cfg(feature = "alloc")]
+pub mod handshake;
+#[cfg(feature = "alloc")]
+pub mod io;
+pub mod mask;
+#[cfg(feature = "alloc")]
+pub use budget::MemoryBudget;
+#[cfg(feature = "alloc")]
+pub use connection::Connection;
+pub use error::ProtocolError;
 #[doc(hidden)]
 pub use frame::Frame;
-pub use ws::WebSocket;
+#[cfg(feature = "alloc")]
+pub use prepared::PreparedFrame;
+#[cfg(feature = "tokio")]
+pub use ws::{ReadOnly, ReadStream, ReuniteError, WebSocket, WsReader, WsWriter};
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+/// Returns random bytes, For example: masking-key.
+///
+/// Without `std`, They are read from [getrandom](https://docs.rs/getrandom/0.2), That needs a custom implementation on targets without an operating system.
+/// There, `getrandom` doesn't compile, Unless `custom` feature is enabled by the final crate: `getrandom = { version = "0.2", features = ["custom"] }`
+#[cfg(feature = "alloc")]
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    #[cfg(feature = "std")]
+    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut bytes);
+    #[cfg(not(feature = "std"))]
+    rand::RngCore::fill_bytes(&mut rand::rngs::OsRng, &mut bytes);
+    bytes
+}
 
 /// Two roles that can be played by a WebSocket connection: `Server` and `Client`.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Role {
     /// Represent websocket server instance.
     Server,
@@ -17,6 +84,31 @@ pub enum Role {
     Client,
 }
 
+/// State of the closing handshake of a websocket connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseState {
+    /// The connection is open, Data can be sent and received.
+    Open,
+    /// A `Close` frame was sent, Waiting for the `Close` frame of the remote endpoint.
+    CloseSent,
+    /// A `Close` frame was received (or the connection failed), And not yet replied.
+    CloseReceived,
+    /// The closing handshake is complete, Or the connection failed.
+    Closed,
+}
+
+impl From<u8> for CloseState {
+    #[inline]
+    fn from(value: u8) -> Self {
+        match value {
+            0 => CloseState::Open,
+            1 => CloseState::CloseSent,
+            2 => CloseState::CloseReceived,
+            _ => CloseState::Closed,
+        }
+    }
+}
+
 /// It represent the type of data that is being sent over the WebSocket connection.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MessageType {
@@ -73,10 +165,26 @@ pub enum DataType {
     Complete(MessageType),
 }
 
+impl DataType {
+    /// Get [MessageType] from [DataType]
+    #[inline]
+    pub fn ty(&self) -> MessageType {
+        match self {
+            DataType::Stream(stream) => stream.ty(),
+            DataType::Complete(ty) => *ty,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
 #[derive(Debug)]
 /// Represent a websocket event
 pub enum Event {
     /// Websocket data frame.
+    ///
+    /// It is only constructed by the [Connection], So the payload of a complete text message is valid UTF-8.
+    /// Match it with `Event::Data { ty, data, .. }`.
+    #[non_exhaustive]
     Data {
         /// Represents WebSocket [DataType], Either complete or fragmented
         ty: DataType,
@@ -98,10 +206,15 @@ pub enum Event {
     /// A Pong frame MAY be sent unsolicited.  This serves as a unidirectional heartbeat.  A response to an unsolicited Pong frame is not expected.
     Pong(Box<[u8]>),
 
-    /// represents the websocket error message.
-    Error(&'static str),
+    /// The remote endpoint violated the websocket protocol, The connection is failed.
+    ///
+    /// The `Close` frame of the corresponding status code is sent, Same as [Event::Close]. (See [Connection::close_on_error])
+    Error(ProtocolError),
 
     /// represents a successful close event of the WebSocket connection.
+    ///
+    /// The `Close` frame is echoed in reply, Before this event is returned by `recv`. So the closing handshake is complete,
+    /// And the stream can be dropped or shutdown with `close`. Except for a read half (`ReadOnly` stream), Where the reply is sent by the write half.
     Close {
         /// represents the status [CloseCode] of the close event.
         code: u16,
@@ -110,8 +223,163 @@ pub enum Event {
     },
 }
 
+#[cfg(feature = "alloc")]
+impl Event {
+    /// Returns the payload of a text data frame, Or `None` for any other event.
+    ///
+    /// Received text is validated incrementally, Invalid UTF-8 fails the connection with [ProtocolError::InvalidUtf8].
+    /// So the payload of a complete text message is returned as is, Without validating it again.
+    ///
+    /// But a code point may be split across fragments (See [DataType::Stream]), Fragment payload is checked,
+    /// And it returns `None` for a fragment that splits a code point. So text is received as complete messages,
+    /// For example: with [Connection::next_message].
+    ///
+    /// ### Example
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "tokio")]
+    /// # async fn run(mut ws: web_socket::WebSocket<tokio::net::TcpStream>) -> std::io::Result<()> {
+    /// let event = ws.recv().await?;
+    /// if let Some(text) = event.as_text() {
+    ///     println!("{text}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            // SAFETY: `Event::Data` is only constructed by the connection, That validates received text.
+            Event::Data {
+                ty: DataType::Complete(MessageType::Text),
+                data,
+            } => Some(unsafe { core::str::from_utf8_unchecked(data) }),
+            Event::Data {
+                ty: DataType::Stream(stream),
+                data,
+            } if stream.ty().is_text() => core::str::from_utf8(data).ok(),
+            _ => None,
+        }
+    }
+
+    /// Same as [Event::as_text], But returns the owned payload.
+    pub fn into_text(self) -> Option<String> {
+        match self {
+            // SAFETY: `Event::Data` is only constructed by the connection, That validates received text.
+            Event::Data {
+                ty: DataType::Complete(MessageType::Text),
+                data,
+            } => Some(unsafe { String::from_utf8_unchecked(data.into()) }),
+            Event::Data {
+                ty: DataType::Stream(stream),
+                data,
+            } if stream.ty().is_text() => String::from_utf8(data.into()).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Owned websocket message, That is sent by the [Sink](https://docs.rs/futures/0.3/futures/sink/trait.Sink.html) implementation of `WebSocket`.
+///
+/// Received messages are [Event]s instead, See [Connection::next_message].
+///
+/// ### Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio")]
+/// # async fn run(ws: web_socket::WebSocket<tokio::net::TcpStream>) -> std::io::Result<()> {
+/// use futures::{SinkExt, StreamExt};
+/// use web_socket::{Event, OutgoingMessage};
+///
+/// let (reader, writer) = ws.split();
+/// reader
+///     .filter_map(|event| async {
+///         match event {
+///             Ok(Event::Data { data, .. }) => Some(Ok(OutgoingMessage::Binary(data.into()))),
+///             Ok(_) => None,
+///             Err(err) => Some(Err(err)),
+///         }
+///     })
+///     .forward(writer)
+///     .await
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutgoingMessage {
+    /// Text data frame.
+    Text(String),
+    /// Binary data frame.
+    Binary(Vec<u8>),
+    /// `Ping` frame, See [Event::Ping].
+    Ping(Vec<u8>),
+    /// `Pong` frame, See [Event::Pong].
+    Pong(Vec<u8>),
+    /// `Close` frame with status [CloseCode] and reason, See [CloseReason].
+    Close {
+        /// Status code of the `Close` frame.
+        code: u16,
+        /// Reason for closing, It must be valid UTF-8.
+        reason: String,
+    },
+}
+
+#[cfg(feature = "alloc")]
+impl From<String> for OutgoingMessage {
+    #[inline]
+    fn from(text: String) -> Self {
+        OutgoingMessage::Text(text)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<&str> for OutgoingMessage {
+    #[inline]
+    fn from(text: &str) -> Self {
+        OutgoingMessage::Text(text.into())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<Vec<u8>> for OutgoingMessage {
+    #[inline]
+    fn from(data: Vec<u8>) -> Self {
+        OutgoingMessage::Binary(data)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<&[u8]> for OutgoingMessage {
+    #[inline]
+    fn from(data: &[u8]) -> Self {
+        OutgoingMessage::Binary(data.into())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl OutgoingMessage {
+    /// Encode the message into the outgoing bytes of `conn`. (See [Connection::encode])
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    fn encode(self, conn: &mut Connection) -> io::Result<()> {
+        let (opcode, data) = match self {
+            OutgoingMessage::Text(text) => (1, text.into_bytes()),
+            OutgoingMessage::Binary(data) => (2, data),
+            OutgoingMessage::Ping(data) => (9, data),
+            OutgoingMessage::Pong(data) => (10, data),
+            OutgoingMessage::Close { code, reason } => {
+                conn.encode_close((code, reason));
+                return Ok(());
+            }
+        };
+        conn.encode(Frame {
+            fin: true,
+            opcode,
+            data: &data,
+        })
+    }
+}
+
 /// When closing an established connection an endpoint MAY indicate a reason for closure.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CloseCode {
     /// The purpose for which the connection was established has been fulfilled
     Normal = 1000,
@@ -210,6 +478,7 @@ impl CloseReason for CloseCode {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl CloseReason for &str {
     type Bytes = Vec<u8>;
     fn to_bytes(self) -> Self::Bytes {
@@ -217,6 +486,7 @@ impl CloseReason for &str {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<Code, Msg> CloseReason for (Code, Msg)
 where
     Code: Into<u16>,
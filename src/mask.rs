@@ -0,0 +1,106 @@
+//! XOR payload with the 4 bytes masking-key, See [RFC 6455 Section 5.3](https://www.rfc-editor.org/rfc/rfc6455#section-5.3)
+//!
+//! Payload is processed 32 (AVX2), 16 (SSE2 / NEON) or 8 (`u64` word) bytes at once,
+//! And the remaining bytes one at a time.
+
+/// Apply (or remove) masking-key to the payload in place.
+///
+/// ### Example
+///
+/// ```rust
+/// use web_socket::mask::apply_mask;
+///
+/// let mut payload = *b"Hello";
+/// apply_mask(&mut payload, [1, 2, 3, 4]);
+/// assert_eq!(payload, [0x49, 0x67, 0x6f, 0x68, 0x6e]);
+/// apply_mask(&mut payload, [1, 2, 3, 4]);
+/// assert_eq!(&payload, b"Hello");
+/// ```
+#[inline]
+pub fn apply_mask(buf: &mut [u8], mask: [u8; 4]) {
+    #[cfg(target_arch = "x86_64")]
+    {
+        // AVX2 is detected at runtime, That requires `std`.
+        #[cfg(feature = "std")]
+        if std::is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 is available at runtime
+            return unsafe { x86::apply_mask_avx2(buf, mask) };
+        }
+        // SAFETY: SSE2 is always available on x86_64
+        unsafe { x86::apply_mask_sse2(buf, mask) }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        // SAFETY: NEON is always available on aarch64
+        unsafe { neon::apply_mask_neon(buf, mask) }
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    apply_mask_word(buf, mask)
+}
+
+/// Masks 8 bytes at once, Using `u64` words.
+///
+/// `mask` is the same for every chunk, Because the chunk size is a multiple of 4.
+#[inline]
+fn apply_mask_word(buf: &mut [u8], mask: [u8; 4]) {
+    let [a, b, c, d] = mask;
+    let word = u64::from_ne_bytes([a, b, c, d, a, b, c, d]);
+
+    let mut chunks = buf.chunks_exact_mut(8);
+    for chunk in &mut chunks {
+        let bytes: &mut [u8; 8] = chunk.try_into().unwrap();
+        *bytes = (u64::from_ne_bytes(*bytes) ^ word).to_ne_bytes();
+    }
+    apply_mask_bytewise(chunks.into_remainder(), mask);
+}
+
+#[inline]
+fn apply_mask_bytewise(buf: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte ^= mask[i & 3];
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use core::arch::x86_64::*;
+
+    #[cfg(feature = "std")]
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn apply_mask_avx2(buf: &mut [u8], mask: [u8; 4]) {
+        let key = _mm256_set1_epi32(i32::from_ne_bytes(mask));
+        let mut chunks = buf.chunks_exact_mut(32);
+        for chunk in &mut chunks {
+            let ptr = chunk.as_mut_ptr() as *mut __m256i;
+            _mm256_storeu_si256(ptr, _mm256_xor_si256(_mm256_loadu_si256(ptr), key));
+        }
+        super::apply_mask_word(chunks.into_remainder(), mask);
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn apply_mask_sse2(buf: &mut [u8], mask: [u8; 4]) {
+        let key = _mm_set1_epi32(i32::from_ne_bytes(mask));
+        let mut chunks = buf.chunks_exact_mut(16);
+        for chunk in &mut chunks {
+            let ptr = chunk.as_mut_ptr() as *mut __m128i;
+            _mm_storeu_si128(ptr, _mm_xor_si128(_mm_loadu_si128(ptr), key));
+        }
+        super::apply_mask_word(chunks.into_remainder(), mask);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use core::arch::aarch64::*;
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn apply_mask_neon(buf: &mut [u8], mask: [u8; 4]) {
+        let key = vreinterpretq_u8_u32(vdupq_n_u32(u32::from_ne_bytes(mask)));
+        let mut chunks = buf.chunks_exact_mut(16);
+        for chunk in &mut chunks {
+            let ptr = chunk.as_mut_ptr();
+            vst1q_u8(ptr, veorq_u8(vld1q_u8(ptr), key));
+        }
+        super::apply_mask_word(chunks.into_remainder(), mask);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::{extension::Offer, Frame};
+use alloc::sync::Arc;
+
+/// A frame that is encoded once, And can be sent to any number of websockets. (See [WebSocket::send_prepared](crate::WebSocket::send_prepared))
+///
+/// Encoded frame is backed by `Arc<[u8]>`, So cloning is cheap.
+///
+/// - Server role: Encoded frame is written as is.
+/// - Client role: Frame is re-masked with a new masking-key.
+///
+/// ### Example
+///
+/// ```no_run
+/// # #[cfg(feature = "tokio")]
+/// # async fn run(clients: &mut [web_socket::WebSocket<tokio::net::TcpStream>]) -> std::io::Result<()> {
+/// use web_socket::PreparedFrame;
+///
+/// let frame = PreparedFrame::new(r#"{"event":"tick"}"#);
+/// for ws in clients {
+///     ws.send_prepared(&frame).await?;
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PreparedFrame {
+    plain: Encoded,
+    /// Compressed frame, And the parameters it is compressed with.
+    compressed: Option<(Encoded, Offer)>,
+}
+
+#[derive(Debug, Clone)]
+struct Encoded {
+    bytes: Arc<[u8]>,
+    head_len: usize,
+}
+
+impl Encoded {
+    fn new(frame: Frame) -> Self {
+        let data_len = frame.data.len();
+        let bytes = frame.encode_without_mask();
+        Self {
+            head_len: bytes.len() - data_len,
+            bytes: bytes.into(),
+        }
+    }
+
+    /// Reserved bits are included in the opcode.
+    fn frame(&self) -> Frame<'_> {
+        Frame {
+            fin: self.bytes[0] & 0x80 != 0,
+            opcode: self.bytes[0] & 0x7f,
+            data: &self.bytes[self.head_len..],
+        }
+    }
+}
+
+impl PreparedFrame {
+    /// Encode a frame without any extension.
+    pub fn new<'a>(frame: impl Into<Frame<'a>>) -> Self {
+        Self {
+            plain: Encoded::new(frame.into()),
+            compressed: None,
+        }
+    }
+
+    /// Encode a frame, Along with a compressed version of it, That is sent to websockets where `permessage-deflate` is negotiated.
+    ///
+    /// Message is compressed without context takeover, Using [PerMessageDeflate::server_max_window_bits](crate::deflate::PerMessageDeflate::server_max_window_bits).
+    /// Websocket whose negotiated window is smaller, receives the uncompressed frame (which is compressed by the websocket itself).
+    ///
+    /// Control frames, Fragmented frames and payloads smaller than [PerMessageDeflate::threshold](crate::deflate::PerMessageDeflate::threshold) are not compressed.
+    #[cfg(feature = "deflate")]
+    pub fn compressed<'a>(
+        frame: impl Into<Frame<'a>>,
+        config: &crate::deflate::PerMessageDeflate,
+    ) -> std::io::Result<Self> {
+        let frame = frame.into();
+        let plain = Encoded::new(Frame {
+            fin: frame.fin,
+            opcode: frame.opcode,
+            data: frame.data,
+        });
+        if !frame.fin || !matches!(frame.opcode, 1 | 2) {
+            return Ok(Self {
+                plain,
+                compressed: None,
+            });
+        }
+        let mut data_frame = crate::extension::DataFrame {
+            fin: frame.fin,
+            rsv: 0,
+            opcode: frame.opcode,
+            data: frame.data.to_vec(),
+        };
+        let params = config.encode_prepared(&mut data_frame)?;
+        let compressed = (data_frame.rsv != 0).then(|| {
+            let frame = Frame {
+                fin: data_frame.fin,
+                opcode: data_frame.rsv | data_frame.opcode,
+                data: &data_frame.data,
+            };
+            (Encoded::new(frame), params)
+        });
+        Ok(Self { plain, compressed })
+    }
+
+    /// Returns the encoded bytes of the uncompressed frame, As it is sent by a server.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.plain.bytes
+    }
+
+    /// Returns the encoded bytes of the compressed frame (if any), As it is sent by a server.
+    pub fn compressed_bytes(&self) -> Option<&[u8]> {
+        self.compressed.as_ref().map(|(encoded, _)| &*encoded.bytes)
+    }
+
+    /// Returns the encoded frame (and the parameters) if it is compressed.
+    pub(crate) fn compressed_parts(&self) -> Option<(Frame<'_>, &[u8], &Offer)> {
+        self.compressed
+            .as_ref()
+            .map(|(encoded, params)| (encoded.frame(), &*encoded.bytes, params))
+    }
+
+    /// Returns the plain frame and its encoded bytes.
+    pub(crate) fn plain_parts(&self) -> (Frame<'_>, &[u8]) {
+        (self.plain.frame(), &self.plain.bytes)
+    }
+}
@@ -0,0 +1,62 @@
+use crate::ProtocolError;
+
+/// Incremental UTF-8 validator for text messages, That are split across fragments.
+///
+/// A code point may be split across frame boundaries, Fragments are passed through as they are received.
+/// So bytes of an incomplete code point (at most 3) at the end of a fragment are kept,
+/// And they are checked along with the first bytes of the next fragment.
+#[derive(Debug, Default)]
+pub(crate) struct Utf8Validator {
+    /// Bytes of an incomplete code point, At the end of the previous fragment.
+    carry: [u8; 4],
+    carry_len: usize,
+}
+
+impl Utf8Validator {
+    /// Validate a text fragment, `fin` indicates that this is the final fragment of the message.
+    pub(crate) fn validate(&mut self, data: &[u8], fin: bool) -> Result<(), ProtocolError> {
+        let mut data = data;
+        if self.carry_len > 0 {
+            let len = self.carry_len;
+            let need = (code_point_len(self.carry[0]) - len).min(data.len());
+            self.carry[len..len + need].copy_from_slice(&data[..need]);
+            self.carry_len += need;
+            data = &data[need..];
+
+            let carry = &self.carry[..self.carry_len];
+            match core::str::from_utf8(carry) {
+                Ok(_) => self.carry_len = 0,
+                Err(err) if err.error_len().is_some() => return Err(ProtocolError::InvalidUtf8),
+                // The fragment is too short, To complete the code point.
+                Err(_) if fin => return Err(ProtocolError::InvalidUtf8),
+                Err(_) => return Ok(()),
+            }
+        }
+        match core::str::from_utf8(data) {
+            Ok(_) => Ok(()),
+            // invalid sequence, Or incomplete code point at the end of the message.
+            Err(err) if err.error_len().is_some() || fin => Err(ProtocolError::InvalidUtf8),
+            Err(err) => {
+                let incomplete = &data[err.valid_up_to()..];
+                self.carry[..incomplete.len()].copy_from_slice(incomplete);
+                self.carry_len = incomplete.len();
+                Ok(())
+            }
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.carry_len = 0;
+    }
+}
+
+/// Returns the length of a code point, That starts with `byte`.
+///
+/// `byte` is the first byte of an incomplete (but valid so far) code point, So it is `2..=4`.
+fn code_point_len(byte: u8) -> usize {
+    match byte {
+        0xf0.. => 4,
+        0xe0.. => 3,
+        _ => 2,
+    }
+}
@@ -0,0 +1,418 @@
+//! Methods that are shared by every `WebSocket`, Over a [Connection](crate::Connection).
+//!
+//! Each backend defines its own `WebSocket<IO>` struct (with `stream` and `conn` fields), And the I/O primitives
+//! that the generated methods are built on. Blocking methods are generated from the same code as `async` ones.
+
+/// Implements `Deref<Target = Connection>`, The constructors and `From<(IO, Role)>`.
+///
+/// Fields other than `stream` and `conn` are initialized with the given values.
+macro_rules! impl_websocket {
+    ($($field: ident: $value: expr),* $(,)?) => {
+        impl<IO> core::ops::Deref for WebSocket<IO> {
+            type Target = Connection;
+
+            #[inline]
+            fn deref(&self) -> &Connection {
+                &self.conn
+            }
+        }
+
+        impl<IO> core::ops::DerefMut for WebSocket<IO> {
+            #[inline]
+            fn deref_mut(&mut self) -> &mut Connection {
+                &mut self.conn
+            }
+        }
+
+        impl<IO> WebSocket<IO> {
+            /// Create a new websocket client instance.
+            #[inline]
+            pub fn client(stream: IO) -> Self {
+                Self::from((stream, Role::Client))
+            }
+
+            /// Create a websocket server instance.
+            #[inline]
+            pub fn server(stream: IO) -> Self {
+                Self::from((stream, Role::Server))
+            }
+
+            /// Create a websocket instance, that reads `buffered` bytes before reading from `stream`.
+            ///
+            /// Useful when websocket frames were already read (buffered) while parsing the handshake.
+            pub fn from_parts(stream: IO, role: Role, buffered: Vec<u8>) -> Self {
+                let mut ws = Self::from((stream, role));
+                ws.conn.receive(&buffered);
+                ws
+            }
+
+            /// Returns the underlying stream and the buffered bytes, that are not yet read.
+            ///
+            /// Bytes of a partially received frame are included in the buffered bytes.
+            pub fn into_parts(self) -> (IO, Vec<u8>) {
+                (self.stream, self.conn.into_buffered())
+            }
+
+            /// Apply handshake parameters, that were agreed by both endpoints.
+            pub fn with_negotiated(self, negotiated: handshake::Negotiated) -> Self {
+                Self {
+                    conn: self.conn.with_negotiated(negotiated),
+                    ..self
+                }
+            }
+        }
+
+        impl<IO> From<(IO, Role)> for WebSocket<IO> {
+            #[inline]
+            fn from((stream, role): (IO, Role)) -> Self {
+                Self {
+                    stream,
+                    conn: Connection::new(role),
+                    $($field: $value,)*
+                }
+            }
+        }
+    };
+}
+
+/// Implements `send`, `send_prepared`, `send_ping`, `send_pong`, `send_close` and `flush`.
+///
+/// Frames are written immediately, By `write_outgoing` and `flush_stream` of the backend.
+macro_rules! impl_send {
+    (blocking) => { impl_send!(@impl); };
+    (async) => { impl_send!(@impl async await); };
+    (@impl $($async: ident $await: ident)?) => {
+        /// Send message to a endpoint.
+        pub $($async)? fn send<'a>(&mut self, data: impl Into<Frame<'a>>) -> Result<()> {
+            let result = self.conn.encode(data);
+            // The reply to the received `Close` frame is written, Even if it fails.
+            self.write_outgoing()$(.$await)??;
+            result
+        }
+
+        /// Send a frame, That is encoded ahead of time. (See [PreparedFrame])
+        pub $($async)? fn send_prepared(&mut self, prepared: &PreparedFrame) -> Result<()> {
+            let result = self.conn.encode_prepared(prepared);
+            self.write_outgoing()$(.$await)??;
+            result
+        }
+
+        /// Send a `Ping` frame, That may serve either as a keepalive or to verify that the remote endpoint is still responsive.
+        pub $($async)? fn send_ping(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+            let result = self.conn.encode_ping(data);
+            self.write_outgoing()$(.$await)??;
+            result
+        }
+
+        /// Send a `Pong` frame, With the payload of the `Ping` frame that is replied to. Or unsolicited, As a unidirectional heartbeat.
+        pub $($async)? fn send_pong(&mut self, data: impl AsRef<[u8]>) -> Result<()> {
+            let result = self.conn.encode_pong(data);
+            self.write_outgoing()$(.$await)??;
+            result
+        }
+
+        /// Send a `Close` frame, Without waiting for the `Close` frame of the remote endpoint.
+        ///
+        /// - If a `Close` frame was already received, It is a reply, And an empty `reason` echoes the received status code.
+        /// - Does nothing, If a `Close` frame was already sent.
+        ///
+        /// Use [WebSocket::close] to perform the complete closing handshake.
+        pub $($async)? fn send_close<T>(&mut self, reason: T) -> Result<()>
+        where
+            T: CloseReason,
+            T::Bytes: AsRef<[u8]>,
+        {
+            self.conn.encode_close(reason);
+            self.write_outgoing()$(.$await)??;
+            self.flush_stream()$(.$await)?
+        }
+
+        /// Flushes this output stream, ensuring that all intermediately buffered contents reach their destination.
+        ///
+        /// If a `Close` frame was received, And not yet replied, The reply is sent.
+        pub $($async)? fn flush(&mut self) -> Result<()> {
+            self.conn.echo_close();
+            self.write_outgoing()$(.$await)??;
+            self.flush_stream()$(.$await)?
+        }
+    };
+}
+
+/// Implements `recv`, `recv_message` and `recv_event`, Over `fill` of the backend.
+///
+/// Documentation of `recv` is given, To describe how the backend waits and resumes.
+macro_rules! impl_recv {
+    (blocking; $($docs: tt)*) => { impl_recv!(@impl []; $($docs)*); };
+    (async; $($docs: tt)*) => { impl_recv!(@impl [async await]; $($docs)*); };
+    (
+        @impl [$($async: ident $await: ident)?];
+        $(#[$recv: meta])* fn recv;
+    ) => {
+        $(#[$recv])*
+        ///
+        /// If a `Close` frame is received (Or the remote endpoint violated the protocol, See [Connection::close_on_error]),
+        /// The `Close` frame is written in reply, Before the event is returned.
+        pub $($async)? fn recv(&mut self) -> Result<Event> {
+            if self.closing.is_none() {
+                self.conn.ensure_readable()?;
+            }
+            self.recv_event()$(.$await)?
+        }
+
+        /// reads [Event] from websocket stream, Where fragments are reassembled into a single message.
+        ///
+        /// Returned event is never a fragment, Data event is always [DataType::Complete].
+        /// See [Connection::next_message] for the events that are returned.
+        pub $($async)? fn recv_message(&mut self) -> Result<Event> {
+            if self.closing.is_none() {
+                self.conn.ensure_readable()?;
+            }
+            self.recv_with(Connection::next_message)$(.$await)?
+        }
+
+        /// reads [Event] from websocket stream, Same as [WebSocket::recv].
+        ///
+        /// But it doesn't return an error, If the `Close` frame was already received.
+        pub $($async)? fn recv_event(&mut self) -> Result<Event> {
+            self.recv_with(Connection::next_event)$(.$await)?
+        }
+
+        /// Parse an event with `next`, Reading from the stream until it is complete.
+        $($async)? fn recv_with(
+            &mut self,
+            next: fn(&mut Connection) -> Result<Option<Event>>,
+        ) -> Result<Event> {
+            let event = match self.closing.take() {
+                Some(event) => event,
+                None => loop {
+                    if let Some(event) = next(&mut self.conn)? {
+                        break event;
+                    }
+                    self.fill()$(.$await)??;
+                },
+            };
+            if let Event::Close { .. } | Event::Error(_) = event {
+                // The event is kept until the reply is written, So it isn't lost if the call is interrupted meanwhile.
+                self.closing = Some(event);
+                // Write error is ignored (For example: the remote endpoint has shutdown the stream),
+                // The event is returned anyway. And the unwritten reply is sent by the next write.
+                let _ = self.flush()$(.$await)?;
+                return Ok(self.closing.take().expect("`Close` or `Error` event"));
+            }
+            Ok(event)
+        }
+    };
+}
+
+/// Implements `close`, Over `wait_for_close` and `shutdown` of the backend.
+///
+/// Every backend ends the closing handshake the same way: A receive error ends the wait,
+/// The stream is shut down anyway, And the error is returned.
+/// Documentation of `close` is given, To describe how the backend limits the wait.
+macro_rules! impl_close {
+    (blocking; $($docs: tt)*) => { impl_close!(@impl []; $($docs)*); };
+    (async; $($docs: tt)*) => { impl_close!(@impl [async await]; $($docs)*); };
+    (@impl [$($async: ident $await: ident)?]; $(#[$close: meta])*) => {
+        /// Perform the closing handshake, And shutdown the underlying stream.
+        ///
+        /// - Sends a `Close` frame with `reason`, Or replies to the received `Close` frame. (See [WebSocket::send_close])
+        /// - Waits for the `Close` frame of the remote endpoint, Data frames that are received meanwhile are discarded.
+        ///
+        $(#[$close])*
+        ///
+        /// If receiving fails (For example: the remote endpoint closed the stream without a `Close` frame),
+        /// The wait ends. The stream is shutdown anyway, And the error is returned.
+        pub $($async)? fn close<T>(mut self, reason: T) -> Result<()>
+        where
+            T: CloseReason,
+            T::Bytes: AsRef<[u8]>,
+        {
+            self.send_close(reason)$(.$await)??;
+            let result = self.wait_for_close()$(.$await)?;
+            self.shutdown()$(.$await)??;
+            result
+        }
+
+        /// Receive frames, Until the `Close` frame of the remote endpoint.
+        $($async)? fn recv_close(&mut self) -> Result<()> {
+            while self.close_state() == CloseState::CloseSent {
+                self.recv_event()$(.$await)??;
+            }
+            Ok(())
+        }
+    };
+}
+
+/// Implements `recv`, `recv_message` and `recv_event`, And their `poll_*` variants. Over `poll_fill` of the backend.
+///
+/// - `poll_reply` writes the reply to a received `Close` frame.
+/// - `poll_delayed` writes the queued frames, Once they are due. Its write error is kept for the next write.
+///
+/// Documentation of `recv` and `recv_message` is given, To describe how the backend waits and resumes.
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+macro_rules! impl_poll_recv {
+    (
+        $(#[$recv: meta])* fn recv;
+        $(#[$message: meta])* fn recv_message;
+    ) => {
+        $(#[$recv])*
+        ///
+        /// If a `Close` frame is received (Or the remote endpoint violated the protocol, See [Connection::close_on_error]),
+        /// The `Close` frame is written in reply, Before the event is returned.
+        pub async fn recv(&mut self) -> Result<Event> {
+            poll_fn(|cx| self.poll_recv(cx)).await
+        }
+
+        /// Attempt to read [Event] from websocket stream, Same as [WebSocket::recv].
+        pub fn poll_recv(&mut self, cx: &mut Context) -> Poll<Result<Event>> {
+            if self.closing.is_none() {
+                self.conn.ensure_readable()?;
+            }
+            self.poll_recv_event(cx)
+        }
+
+        /// reads [Event] from websocket stream, Where fragments are reassembled into a single message.
+        ///
+        /// Returned event is never a fragment, Data event is always [DataType::Complete].
+        /// See [Connection::next_message] for the events that are returned.
+        ///
+        /// This method is cancellation safe, Received fragments are kept in the websocket.
+        $(#[$message])*
+        pub async fn recv_message(&mut self) -> Result<Event> {
+            poll_fn(|cx| self.poll_recv_message(cx)).await
+        }
+
+        /// Attempt to read [Event] from websocket stream, Same as [WebSocket::recv_message].
+        pub fn poll_recv_message(&mut self, cx: &mut Context) -> Poll<Result<Event>> {
+            if self.closing.is_none() {
+                self.conn.ensure_readable()?;
+            }
+            self.poll_with(cx, Connection::next_message)
+        }
+
+        /// reads [Event] from websocket stream, Same as [WebSocket::recv].
+        ///
+        /// But it doesn't return an error, If the `Close` frame was already received.
+        pub async fn recv_event(&mut self) -> Result<Event> {
+            poll_fn(|cx| self.poll_recv_event(cx)).await
+        }
+
+        /// Attempt to read [Event] from websocket stream, Same as [WebSocket::recv_event].
+        pub fn poll_recv_event(&mut self, cx: &mut Context) -> Poll<Result<Event>> {
+            self.poll_with(cx, Connection::next_event)
+        }
+
+        /// Parse an event with `next`, Reading from the stream until it is complete.
+        fn poll_with(
+            &mut self,
+            cx: &mut Context,
+            next: fn(&mut Connection) -> Result<Option<Event>>,
+        ) -> Poll<Result<Event>> {
+            self.poll_delayed(cx);
+            let event = match self.closing.take() {
+                Some(event) => event,
+                None => loop {
+                    if let Some(event) = next(&mut self.conn)? {
+                        break event;
+                    }
+                    ready!(self.poll_fill(cx))?;
+                },
+            };
+            if let Event::Close { .. } | Event::Error(_) = event {
+                // The event is kept, Until the reply is written.
+                if self.poll_reply(cx).is_pending() {
+                    self.closing = Some(event);
+                    return Poll::Pending;
+                }
+                // Write error is ignored (For example: the remote endpoint has shutdown the stream), The event is returned anyway.
+            }
+            Poll::Ready(Ok(event))
+        }
+    };
+}
+
+/// Implements `Stream` and `FusedStream`, That yield complete messages. (See `impl_poll_recv`)
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+macro_rules! impl_stream {
+    ($io: ident: $($bounds: tt)+) => {
+        /// Yields complete messages, Same as [WebSocket::recv_message].
+        ///
+        /// The stream ends after the `Close` event, Or after an error of the underlying stream.
+        impl<$io> Stream for WebSocket<$io>
+        where
+            $io: $($bounds)+,
+        {
+            type Item = Result<Event>;
+
+            fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+                let this = self.get_mut();
+                if this.is_terminated() {
+                    return Poll::Ready(None);
+                }
+                this.poll_recv_message(cx).map(Some)
+            }
+        }
+
+        impl<$io> FusedStream for WebSocket<$io>
+        where
+            $io: $($bounds)+,
+        {
+            fn is_terminated(&self) -> bool {
+                let closed = matches!(
+                    self.close_state(),
+                    CloseState::CloseReceived | CloseState::Closed
+                );
+                closed && self.closing.is_none()
+            }
+        }
+    };
+}
+
+/// Implements `Sink<OutgoingMessage>`.
+///
+/// Over `poll_write_due`, `encode_message`, `poll_write_outgoing` and `poll_shutdown` of the backend.
+/// Documentation of `poll_ready` is given, To describe when the backend writes messages.
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+macro_rules! impl_sink {
+    ($(#[$docs: meta])* $w: ident: $($bounds: tt)+) => {
+        $(#[$docs])*
+        ///
+        /// `poll_close` sends a `Close` frame (if not sent yet) and flushes, But the underlying stream is shut down
+        /// only after the closing handshake is complete, So the `Close` frame of the remote endpoint can still be received.
+        ///
+        /// Inherent methods (`send`, `flush`, `close`) take precedence over the methods of `SinkExt`,
+        /// So call them as `SinkExt::send(&mut ws, message)`.
+        impl<$w> Sink<OutgoingMessage> for WebSocket<$w>
+        where
+            $w: $($bounds)+,
+        {
+            type Error = io::Error;
+
+            fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+                self.get_mut().poll_write_due(cx)
+            }
+
+            fn start_send(self: Pin<&mut Self>, message: OutgoingMessage) -> Result<()> {
+                self.get_mut().encode_message(message)
+            }
+
+            fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+                let this = self.get_mut();
+                this.conn.echo_close();
+                ready!(this.poll_write_outgoing(cx))?;
+                Pin::new(&mut this.stream).poll_flush(cx)
+            }
+
+            fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+                let this = self.get_mut();
+                this.conn.encode_close(());
+                ready!(this.poll_write_outgoing(cx))?;
+                ready!(Pin::new(&mut this.stream).poll_flush(cx))?;
+                if this.close_state() != CloseState::Closed {
+                    return Poll::Ready(Ok(()));
+                }
+                this.poll_shutdown(cx)
+            }
+        }
+    };
+}
@@ -1,34 +1,230 @@
-#![allow(clippy::unusual_byte_groupings)]
-use crate::*;
-use std::io::{IoSlice, Result};
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use crate::{
+    connection::{encode_frame, encode_head},
+    mask, *,
+};
+use futures_core::{FusedStream, Stream};
+use futures_sink::Sink;
+use std::{
+    fmt,
+    future::{poll_fn, Future},
+    io::{self, ErrorKind, IoSlice, Result},
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf, ReadHalf, WriteHalf};
+use tokio::time::{sleep_until, Instant, Sleep};
 
 /// WebSocket implementation for both client and server
+///
+/// It drives a [Connection] with `tokio` streams, Protocol state and its configuration
+/// (For example: [Connection::max_payload_len]) are accessible through `Deref`.
+///
+/// ### Example
+///
+/// Frames that were read (buffered) while parsing the handshake, Are read first. (See [WebSocket::from_parts])
+///
+/// ```rust
+/// use web_socket::{Role, WebSocket};
+/// let ws = WebSocket::from_parts(tokio::io::empty(), Role::Client, vec![0x81, 0]);
+/// assert_eq!(ws.buffered(), [0x81, 0]);
+/// ```
 #[derive(Debug)]
 pub struct WebSocket<Stream> {
     /// it is a low-level abstraction that represents the underlying byte stream over which WebSocket messages are exchanged.
     pub stream: Stream,
 
-    /// Maximum allowed payload length in bytes.
+    /// Maximum time [WebSocket::close] waits for the `Close` frame of the remote endpoint, before shutting down the stream.
     ///
-    /// Default: 16 MB
-    pub max_payload_len: usize,
+    /// Default: 5 seconds
+    pub close_timeout: Duration,
 
-    role: Role,
-    is_closed: bool,
-    fragment: Option<MessageType>,
+    /// Data frames are queued, Until the queued frames reach this size in bytes. Then they are written together.
+    ///
+    /// Queued frames are also written by [WebSocket::flush], Or after [WebSocket::write_delay].
+    /// `Ping` and `Pong` frames jump the queue, And `Close` frame is written after the queued frames.
+    /// Set to `0` to write every frame immediately.
+    ///
+    /// Default: `0`
+    pub write_buffer_size: usize,
+
+    /// Maximum time a data frame stays in the queue.
+    ///
+    /// Due frames are written by the next `send`, Or while `recv` is pending. So nothing else needs to happen after a `send`.
+    /// If writing them fails while `recv` is pending, The error is returned by the next write (For example: `send` or `flush`).
+    /// Except for a write half (See [WsWriter]), That isn't received from. Where [WebSocket::flush_delayed] writes them.
+    ///
+    /// Default: `None`
+    pub write_delay: Option<Duration>,
+
+    conn: Connection,
+    /// Scratch buffer for encoding frames, It is reused across writes.
+    write_buf: Vec<u8>,
+    /// Time when the queued frames are due.
+    flush_at: Option<Instant>,
+    /// Timer of the due frames, That is polled by `recv`.
+    timer: Option<Pin<Box<Sleep>>>,
+    /// `Close` (or `Error`) event, That is returned once the `Close` frame is written in reply.
+    closing: Option<Event>,
+    /// Error of writing the due frames while `recv` is pending, That is returned by the next write.
+    write_error: Option<io::Error>,
 }
 
-impl<IO> WebSocket<IO> {
-    /// Create a new websocket client instance.
+/// Read half of a [WebSocket], Created by [WebSocket::split].
+pub type WsReader<IO> = WebSocket<ReadOnly<ReadHalf<IO>>>;
+
+/// A stream that is only read, For example: read half of a split stream.
+///
+/// A websocket over it can't reply to a received `Close` frame. So the reply is sent by the write half,
+/// On its next write or [WebSocket::flush].
+#[derive(Debug)]
+pub struct ReadOnly<R>(pub R);
+
+impl<R: Unpin + AsyncRead> AsyncRead for ReadOnly<R> {
+    #[inline]
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_read(cx, buf)
+    }
+}
+
+/// A stream that websocket frames are received from.
+///
+/// It is implemented for streams that are also written, And for [ReadOnly] streams.
+pub trait ReadStream: Unpin + AsyncRead + sealed::Sealed {
+    /// Write the reply to a received `Close` frame (if any), Along with the queued frames.
+    #[doc(hidden)]
+    fn poll_reply(ws: &mut WebSocket<Self>, cx: &mut Context) -> Poll<Result<()>>
+    where
+        Self: Sized;
+
+    /// Write the queued frames, Once they are due. (See [WebSocket::write_delay])
+    #[doc(hidden)]
+    fn poll_delayed(ws: &mut WebSocket<Self>, cx: &mut Context) -> Poll<Result<()>>
+    where
+        Self: Sized;
+}
+
+impl<IO: Unpin + AsyncRead + AsyncWrite> ReadStream for IO {
+    fn poll_reply(ws: &mut WebSocket<Self>, cx: &mut Context) -> Poll<Result<()>> {
+        ws.conn.echo_close();
+        ready!(ws.poll_write_outgoing(cx))?;
+        Pin::new(&mut ws.stream).poll_flush(cx)
+    }
+
+    fn poll_delayed(ws: &mut WebSocket<Self>, cx: &mut Context) -> Poll<Result<()>> {
+        let Some(at) = ws.flush_at else {
+            return Poll::Ready(Ok(()));
+        };
+        let timer = ws.timer.get_or_insert_with(|| Box::pin(sleep_until(at)));
+        if timer.deadline() != at {
+            timer.as_mut().reset(at);
+        }
+        ready!(timer.as_mut().poll(cx));
+        ready!(ws.poll_write_outgoing(cx))?;
+        Pin::new(&mut ws.stream).poll_flush(cx)
+    }
+}
+
+impl<R: Unpin + AsyncRead> ReadStream for ReadOnly<R> {
     #[inline]
-    pub fn client(stream: IO) -> Self {
-        Self::from((stream, Role::Client))
+    fn poll_reply(_: &mut WebSocket<Self>, _: &mut Context) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
     }
-    /// Create a websocket server instance.
+
     #[inline]
-    pub fn server(stream: IO) -> Self {
-        Self::from((stream, Role::Server))
+    fn poll_delayed(_: &mut WebSocket<Self>, _: &mut Context) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl<IO: Unpin + tokio::io::AsyncRead + tokio::io::AsyncWrite> Sealed for IO {}
+    impl<R> Sealed for super::ReadOnly<R> {}
+}
+
+/// Write half of a [WebSocket], Created by [WebSocket::split].
+pub type WsWriter<IO> = WebSocket<WriteHalf<IO>>;
+
+impl_websocket!(
+    close_timeout: Duration::from_secs(5),
+    write_buffer_size: 0,
+    write_delay: None,
+    write_buf: Vec::new(),
+    flush_at: None,
+    timer: None,
+    closing: None,
+    write_error: None,
+);
+
+impl<IO> WebSocket<IO> {
+    /// Split the websocket into independent read and write halves, using [tokio::io::split].
+    ///
+    /// The halves can be used concurrently (For example: in separate tasks), And can be joined with [WebSocket::reunite].
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # async fn run(ws: web_socket::WebSocket<tokio::net::TcpStream>) -> std::io::Result<()> {
+    /// let (mut reader, mut writer) = ws.split();
+    /// tokio::spawn(async move {
+    ///     // A `Close` frame that is received by the read half, Is replied by the next write of the write half.
+    ///     // Then `send_ping` fails, As the websocket is closed.
+    ///     while writer.send_ping("heartbeat").await.is_ok() {
+    ///         tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+    ///     }
+    /// });
+    /// while let Ok(event) = reader.recv().await {
+    ///     println!("{event:?}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn split(self) -> (WsReader<IO>, WsWriter<IO>)
+    where
+        IO: AsyncRead + AsyncWrite,
+    {
+        self.split_with(tokio::io::split)
+    }
+
+    /// Split the websocket into read and write halves, using `f` to split the underlying stream.
+    ///
+    /// For example: `ws.split_with(TcpStream::into_split)`
+    pub fn split_with<R, W>(
+        self,
+        f: impl FnOnce(IO) -> (R, W),
+    ) -> (WebSocket<ReadOnly<R>>, WebSocket<W>) {
+        let (reader, writer) = f(self.stream);
+        let (read_conn, write_conn) = self.conn.split();
+        let writer = WebSocket {
+            stream: writer,
+            close_timeout: self.close_timeout,
+            write_buffer_size: self.write_buffer_size,
+            write_delay: self.write_delay,
+            conn: write_conn,
+            write_buf: self.write_buf,
+            flush_at: self.flush_at,
+            timer: None,
+            closing: None,
+            write_error: self.write_error,
+        };
+        let reader = WebSocket {
+            stream: ReadOnly(reader),
+            close_timeout: self.close_timeout,
+            write_buffer_size: self.write_buffer_size,
+            write_delay: self.write_delay,
+            conn: read_conn,
+            write_buf: Vec::new(),
+            flush_at: None,
+            timer: None,
+            closing: self.closing,
+            write_error: None,
+        };
+        (reader, writer)
     }
 }
 
@@ -38,32 +234,207 @@ where
 {
     #[doc(hidden)]
     pub async fn send_raw(&mut self, frame: Frame<'_>) -> Result<()> {
-        let buf = match self.role {
-            Role::Server => {
-                if self.stream.is_write_vectored() {
-                    let mut head = [0; 10];
-                    let head_len = unsafe { frame.encode_header_unchecked(head.as_mut_ptr(), 0) };
-                    let total_len = head_len + frame.data.len();
-
-                    let mut bufs = [IoSlice::new(&head[..head_len]), IoSlice::new(frame.data)];
-                    let mut amt = self.stream.write_vectored(&bufs).await?;
-                    if amt == total_len {
-                        return Ok(());
-                    }
-                    while amt < head_len {
-                        bufs[0] = IoSlice::new(&head[amt..head_len]);
-                        amt += self.stream.write_vectored(&bufs).await?;
-                    }
-                    if amt < total_len {
-                        self.stream.write_all(&frame.data[amt - head_len..]).await?;
-                    }
-                    return Ok(());
-                }
-                frame.encode_without_mask()
+        self.ensure_open().await?;
+        let corked = self.write_buffer_size > 0;
+        self.send_frame(frame, corked).await?;
+        self.write_due().await
+    }
+
+    /// Send a frame, That is encoded ahead of time. (See [PreparedFrame])
+    ///
+    /// If extensions (other than `permessage-deflate`) are negotiated, The frame is encoded by them as usual.
+    pub async fn send_prepared(&mut self, prepared: &PreparedFrame) -> Result<()> {
+        self.ensure_open().await?;
+        let Some((frame, bytes)) = self.conn.prepared(prepared) else {
+            return self.send_raw(prepared.plain_parts().0).await;
+        };
+        let corked = self.write_buffer_size > 0;
+        match self.role() {
+            Role::Server => self.write_encoded(frame.opcode, bytes, corked).await?,
+            Role::Client => self.write_frame(frame, corked).await?,
+        }
+        self.write_due().await
+    }
+
+    async fn ensure_open(&mut self) -> Result<()> {
+        if let Some(err) = self.write_error.take() {
+            return Err(err);
+        }
+        if let Err(err) = self.conn.ensure_open() {
+            // The reply to the received `Close` frame is written, If any.
+            self.flush().await?;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Encode `frame` with negotiated extensions, Data frame is queued if `queue` is `true`.
+    async fn send_frame(&mut self, frame: Frame<'_>, queue: bool) -> Result<()> {
+        if frame.opcode >= 8 || !self.has_extensions() {
+            return self.write_frame(frame, queue).await;
+        }
+        let frame = self.conn.encode_extensions(frame)?;
+        self.write_frame(Frame::from(&frame), queue).await
+    }
+
+    async fn write_frame(&mut self, frame: Frame<'_>, queue: bool) -> Result<()> {
+        let opcode = frame.opcode & 0x0f;
+        if opcode < 8 && queue {
+            let role = self.role();
+            encode_frame(role, frame, self.queue());
+            return Ok(());
+        }
+        self.write_queued_before(opcode).await?;
+        // Header and payload are written at once with vectored I/O, Otherwise they are copied into `write_buf`.
+        // Client payload is always masked in `write_buf`, So that no memory is allocated per frame.
+        let role = self.role();
+        let buf = &mut self.write_buf;
+        buf.clear();
+        if !self.stream.is_write_vectored() {
+            encode_frame(role, frame, buf);
+            return self.stream.write_all(buf).await;
+        }
+        let mut head = [0; 14];
+        let head_len = encode_head(role, &frame, &mut head);
+        let payload = match role {
+            Role::Server => frame.data,
+            Role::Client => {
+                buf.extend_from_slice(frame.data);
+                let key = head[head_len - 4..head_len].try_into().unwrap();
+                mask::apply_mask(buf, key);
+                buf
             }
-            Role::Client => frame.encode_with(rand::random::<u32>().to_ne_bytes()),
         };
-        self.stream.write_all(&buf).await
+        write_all_vectored(&mut self.stream, &head[..head_len], payload).await
+    }
+
+    /// Write a frame, That is already encoded.
+    async fn write_encoded(&mut self, opcode: u8, bytes: &[u8], queue: bool) -> Result<()> {
+        let opcode = opcode & 0x0f;
+        if opcode < 8 && queue {
+            self.queue().extend_from_slice(bytes);
+            return Ok(());
+        }
+        self.write_queued_before(opcode).await?;
+        self.stream.write_all(bytes).await
+    }
+
+    /// Returns the queue, Where the data frames are encoded.
+    fn queue(&mut self) -> &mut Vec<u8> {
+        self.start_delay();
+        self.conn.queue()
+    }
+
+    /// Start [WebSocket::write_delay] of the frames, That are going to be queued. If the queue is empty.
+    fn start_delay(&mut self) {
+        if self.conn.outgoing().is_empty() {
+            self.flush_at = self.write_delay.map(|delay| Instant::now() + delay);
+        }
+    }
+
+    /// Write the queued frames, Before a frame of `opcode` is written directly.
+    async fn write_queued_before(&mut self, opcode: u8) -> Result<()> {
+        // `Ping` and `Pong` frames jump the queue, Unless the queued frames are partially written.
+        if matches!(opcode, 9 | 10) && !self.conn.is_partially_written() {
+            return Ok(());
+        }
+        self.write_outgoing().await
+    }
+
+    /// Write the queued frames, If they reach [WebSocket::write_buffer_size] or they are due.
+    async fn write_due(&mut self) -> Result<()> {
+        poll_fn(|cx| self.poll_write_due(cx)).await
+    }
+
+    fn poll_write_due(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        let due = self.flush_at.is_some_and(|at| Instant::now() >= at);
+        if due || self.conn.outgoing().len() >= self.write_buffer_size {
+            ready!(self.poll_write_outgoing(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Write the queued frames.
+    ///
+    /// This method is cancellation safe, Written bytes are tracked by the connection.
+    async fn write_outgoing(&mut self) -> Result<()> {
+        poll_fn(|cx| self.poll_write_outgoing(cx)).await
+    }
+
+    fn poll_write_outgoing(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        if let Some(err) = self.write_error.take() {
+            return Poll::Ready(Err(err));
+        }
+        loop {
+            let queued = self.conn.outgoing();
+            if queued.is_empty() {
+                break;
+            }
+            let amt = ready!(Pin::new(&mut self.stream).poll_write(cx, queued))?;
+            if amt == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+            }
+            self.conn.consume_outgoing(amt);
+        }
+        self.flush_at = None;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Data messages are queued, `Ping` and `Pong` messages jump the queue. Same as [WebSocket::send_ping].
+    fn encode_message(&mut self, message: OutgoingMessage) -> Result<()> {
+        match message {
+            OutgoingMessage::Ping(data) => self.conn.encode_ahead(9, &data),
+            OutgoingMessage::Pong(data) => self.conn.encode_ahead(10, &data),
+            message => {
+                self.start_delay();
+                message.encode(&mut self.conn)
+            }
+        }
+    }
+
+    #[inline]
+    fn poll_shutdown(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+
+    /// Send multiple messages, That are encoded into the queue and written together.
+    ///
+    /// ### Example
+    ///
+    /// ```no_run
+    /// # use web_socket::*;
+    /// # async {
+    /// let mut ws = WebSocket::server(Vec::new());
+    /// ws.send_batch(["Hello", "World"]).await;
+    /// # };
+    /// ```
+    pub async fn send_batch<'a, I>(&mut self, frames: I) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Into<Frame<'a>>,
+    {
+        self.ensure_open().await?;
+        for frame in frames {
+            self.send_frame(frame.into(), true).await?;
+        }
+        self.flush().await
+    }
+
+    /// Returns the time when the queued frames are due, If any. (See [WebSocket::write_delay])
+    pub fn flush_deadline(&self) -> Option<Instant> {
+        self.flush_at
+    }
+
+    /// Waits until the queued frames are due (See [WebSocket::write_delay]), And flushes them.
+    ///
+    /// It never completes, If no frame is due. This method is cancellation safe,
+    /// So it can be used in `tokio::select!`, Along with other events.
+    pub async fn flush_delayed(&mut self) -> Result<()> {
+        match self.flush_at {
+            Some(at) => tokio::time::sleep_until(at).await,
+            None => std::future::pending().await,
+        }
+        self.flush().await
     }
 
     /// Send message to a endpoint.
@@ -71,18 +442,20 @@ where
         self.send_raw(data.into()).await
     }
 
+    /// Send a `Close` frame, Without waiting for the `Close` frame of the remote endpoint.
+    ///
     /// - The Close frame MAY contain a body that indicates a reason for closing.
-    pub async fn close<T>(mut self, reason: T) -> Result<()>
+    /// - If a `Close` frame was already received, It is a reply, And an empty `reason` echoes the received status code.
+    /// - Does nothing, If a `Close` frame was already sent.
+    ///
+    /// Use [WebSocket::close] to perform the complete closing handshake.
+    pub async fn send_close<T>(&mut self, reason: T) -> Result<()>
     where
         T: CloseReason,
         T::Bytes: AsRef<[u8]>,
     {
-        self.send_raw(Frame {
-            fin: true,
-            opcode: 8,
-            data: reason.to_bytes().as_ref(),
-        })
-        .await?;
+        self.conn.encode_close(reason);
+        self.write_outgoing().await?;
         self.stream.flush().await
     }
 
@@ -123,219 +496,165 @@ where
     }
 
     /// Flushes this output stream, ensuring that all intermediately buffered contents reach their destination.
+    ///
+    /// Queued frames are written first. If a `Close` frame was received, And not yet replied, The reply is sent after them.
     pub async fn flush(&mut self) -> Result<()> {
+        self.conn.echo_close();
+        self.write_outgoing().await?;
         self.stream.flush().await
     }
 }
 
-// ------------------------------------------------------------------------
-
-macro_rules! err { [$msg: expr] => { return Ok(Event::Error($msg)) }; }
-
-#[inline]
-pub async fn read_buf<const N: usize, R>(stream: &mut R) -> Result<[u8; N]>
+impl<IO> WebSocket<IO>
 where
-    R: Unpin + AsyncRead,
+    IO: Unpin + AsyncRead + AsyncWrite,
 {
-    let mut buf = [0; N];
-    stream.read_exact(&mut buf).await?;
-    Ok(buf)
+    impl_close! {
+        async;
+        /// Waits at most [WebSocket::close_timeout], Then it returns [ErrorKind::TimedOut] error.
+    }
+
+    /// Receive frames until the `Close` frame of the remote endpoint, Within [WebSocket::close_timeout].
+    async fn wait_for_close(&mut self) -> Result<()> {
+        match tokio::time::timeout(self.close_timeout, self.recv_close()).await {
+            Ok(result) => result,
+            Err(_) => Err(ErrorKind::TimedOut.into()),
+        }
+    }
+
+    #[inline]
+    async fn shutdown(&mut self) -> Result<()> {
+        self.stream.shutdown().await
+    }
 }
 
-impl<R> WebSocket<R>
+/// Write header and payload, Using vectored I/O.
+async fn write_all_vectored<W>(stream: &mut W, head: &[u8], payload: &[u8]) -> Result<()>
 where
-    R: Unpin + AsyncRead,
+    W: Unpin + AsyncWrite,
 {
-    /// reads [Event] from websocket stream.
-    pub async fn recv(&mut self) -> Result<Event> {
-        if self.is_closed {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::NotConnected,
-                "read after close",
-            ));
-        }
-        let event = self.recv_event().await;
-        if let Ok(Event::Close { .. } | Event::Error(..)) | Err(..) = event {
-            self.is_closed = true;
-        }
-        event
-    }
-
-    // ### WebSocket Frame Header
-    //
-    // ```txt
-    //  0                   1                   2                   3
-    //  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
-    // +-+-+-+-+-------+-+-------------+-------------------------------+
-    // |F|R|R|R| opcode|M| Payload len |    Extended payload length    |
-    // |I|S|S|S|  (4)  |A|     (7)     |             (16/64)           |
-    // |N|V|V|V|       |S|             |   (if payload len==126/127)   |
-    // | |1|2|3|       |K|             |                               |
-    // +-+-+-+-+-------+-+-------------+ - - - - - - - - - - - - - - - +
-    // |     Extended payload length continued, if payload len == 127  |
-    // + - - - - - - - - - - - - - - - +-------------------------------+
-    // |                               |Masking-key, if MASK set to 1  |
-    // +-------------------------------+-------------------------------+
-    // | Masking-key (continued)       |          Payload Data         |
-    // +-------------------------------- - - - - - - - - - - - - - - - +
-    // :                     Payload Data continued ...                :
-    // + - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - +
-    // |                     Payload Data continued ...                |
-    // +---------------------------------------------------------------+
-    // ```
-    /// reads [Event] from websocket stream.
-    pub async fn recv_event(&mut self) -> Result<Event> {
-        let [b1, b2] = read_buf(&mut self.stream).await?;
-
-        let fin = b1 & 0b_1000_0000 != 0;
-        let rsv = b1 & 0b_111_0000;
-        let opcode = b1 & 0b_1111;
-        let len = (b2 & 0b_111_1111) as usize;
-
-        // Defines whether the "Payload data" is masked.  If set to 1, a
-        // masking key is present in masking-key, and this is used to unmask
-        // the "Payload data" as per [Section 5.3](https://datatracker.ietf.org/doc/html/rfc6455#section-5.3).  All frames sent from
-        // client to server have this bit set to 1.
-        let is_masked = b2 & 0b_1000_0000 != 0;
-
-        if rsv != 0 {
-            // MUST be `0` unless an extension is negotiated that defines meanings
-            // for non-zero values.  If a nonzero value is received and none of
-            // the negotiated extensions defines the meaning of such a nonzero
-            // value, the receiving endpoint MUST _Fail the WebSocket Connection_.
-            err!("reserve bit must be `0`");
-        }
+    let total_len = head.len() + payload.len();
+    let mut bufs = [IoSlice::new(head), IoSlice::new(payload)];
+    let mut amt = stream.write_vectored(&bufs).await?;
+    if amt == total_len {
+        return Ok(());
+    }
+    while amt < head.len() {
+        bufs[0] = IoSlice::new(&head[amt..]);
+        amt += stream.write_vectored(&bufs).await?;
+    }
+    if amt < total_len {
+        stream.write_all(&payload[amt - head.len()..]).await?;
+    }
+    Ok(())
+}
 
-        // A client MUST mask all frames that it sends to the server. (Note
-        // that masking is done whether or not the WebSocket Protocol is running
-        // over TLS.)  The server MUST close the connection upon receiving a
-        // frame that is not masked.
-        //
-        // A server MUST NOT mask any frames that it sends to the client.
-        if let Role::Server = self.role {
-            if !is_masked {
-                err!("expected masked frame");
-            }
-        } else if is_masked {
-            err!("expected unmasked frame");
+impl<R: ReadStream> WebSocket<R> {
+    impl_poll_recv! {
+        /// reads [Event] from websocket stream.
+        ///
+        /// This method is cancellation safe. Partially received frame is kept in the websocket,
+        /// So if it is used as an event in `tokio::select!` and some other branch completes first,
+        /// The next call resumes reading exactly where it stopped.
+        ///
+        /// [ReadOnly] streams (For example: [WsReader]) can't reply to a received `Close` frame,
+        /// There the reply is written by the write half.
+        fn recv;
+        ///
+        /// ### Example
+        ///
+        /// ```no_run
+        /// use web_socket::{WebSocket, Event};
+        ///
+        /// # async fn run() -> std::io::Result<()> {
+        /// let mut ws = WebSocket::client(tokio::io::empty());
+        /// while let Event::Data { ty, data, .. } = ws.recv_message().await? {
+        ///     println!("{:?}: {} bytes", ty.ty(), data.len());
+        /// }
+        /// # Ok(()) }
+        /// ```
+        fn recv_message;
+    }
+
+    /// Write error is kept, And returned by the next write.
+    fn poll_delayed(&mut self, cx: &mut Context) {
+        if let Poll::Ready(Err(err)) = R::poll_delayed(self, cx) {
+            self.write_error = Some(err);
         }
+    }
 
-        // 3-7 are reserved for further non-control frames.
-        if opcode >= 8 {
-            if !fin {
-                err!("control frame must not be fragmented");
-            }
-            if len > 125 {
-                err!("control frame must have a payload length of 125 bytes or less");
-            }
-            let msg = self.read_payload(len).await?;
-            match opcode {
-                8 => Ok(on_close(&msg)),
-                9 => Ok(Event::Ping(msg)),
-                10 => Ok(Event::Pong(msg)),
-                // 11-15 are reserved for further control frames
-                _ => err!("unknown opcode"),
+    #[inline]
+    fn poll_reply(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        R::poll_reply(self, cx)
+    }
+
+    /// Read some bytes from the stream, Into the read buffer of the connection.
+    fn poll_fill(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        let mut buf = ReadBuf::new(self.conn.read_buf());
+        let result = ready!(Pin::new(&mut self.stream).poll_read(cx, &mut buf));
+        let amt = buf.filled().len();
+        match result {
+            Ok(()) if amt > 0 => {
+                self.conn.advance(amt);
+                Poll::Ready(Ok(()))
             }
-        } else {
-            let ty = match (opcode, fin, self.fragment) {
-                (2, true, None) => DataType::Complete(MessageType::Binary),
-                (1, true, None) => DataType::Complete(MessageType::Text),
-                (2, false, None) => {
-                    self.fragment = Some(MessageType::Binary);
-                    DataType::Stream(Stream::Start(MessageType::Binary))
-                }
-                (1, false, None) => {
-                    self.fragment = Some(MessageType::Text);
-                    DataType::Stream(Stream::Start(MessageType::Text))
-                }
-                (0, false, Some(ty)) => DataType::Stream(Stream::Next(ty)),
-                (0, true, Some(ty)) => {
-                    self.fragment = None;
-                    DataType::Stream(Stream::End(ty))
-                }
-                _ => err!("invalid data frame"),
-            };
-            let len = match len {
-                126 => u16::from_be_bytes(read_buf(&mut self.stream).await?) as usize,
-                127 => u64::from_be_bytes(read_buf(&mut self.stream).await?) as usize,
-                len => len,
-            };
-            if len > self.max_payload_len {
-                err!("payload too large");
+            result => {
+                self.conn.fail();
+                Poll::Ready(result.and(Err(ErrorKind::UnexpectedEof.into())))
             }
-            let data = self.read_payload(len).await?;
-            Ok(Event::Data { ty, data })
         }
     }
+}
 
-    async fn read_payload(&mut self, len: usize) -> Result<Box<[u8]>> {
-        let mut data = vec![0; len].into_boxed_slice();
-        match self.role {
-            Role::Server => {
-                let mask: [u8; 4] = read_buf(&mut self.stream).await?;
-                self.stream.read_exact(&mut data).await?;
-                // TODO: Use SIMD wherever possible for best performance
-                for i in 0..data.len() {
-                    data[i] ^= mask[i & 3];
-                }
-            }
-            Role::Client => {
-                self.stream.read_exact(&mut data).await?;
-            }
+impl_stream!(R: ReadStream);
+
+impl_sink! {
+    /// Messages are queued, Until the queued frames reach [WebSocket::write_buffer_size] (or they are due).
+    /// So `poll_ready` is pending, While the queued frames are written. `Ping` and `Pong` messages jump the queue.
+    W: Unpin + AsyncWrite
+}
+
+impl<IO: Unpin> WsReader<IO> {
+    /// Join the halves, that were created by [WebSocket::split], back into a websocket.
+    ///
+    /// Returns an error, If the halves are not from the same websocket.
+    pub fn reunite(
+        self,
+        writer: WsWriter<IO>,
+    ) -> std::result::Result<WebSocket<IO>, Box<ReuniteError<IO>>> {
+        if !self.conn.is_pair_of(&writer.conn) || !self.stream.0.is_pair_of(&writer.stream) {
+            return Err(Box::new(ReuniteError(self, writer)));
         }
-        Ok(data)
+        Ok(WebSocket {
+            stream: self.stream.0.unsplit(writer.stream),
+            close_timeout: self.close_timeout,
+            write_buffer_size: self.write_buffer_size,
+            write_delay: self.write_delay,
+            conn: self.conn.unsplit(writer.conn),
+            write_buf: writer.write_buf,
+            flush_at: writer.flush_at,
+            timer: None,
+            closing: self.closing,
+            write_error: writer.write_error,
+        })
     }
 }
 
-/// - If there is a body, the first two bytes of the body MUST be a 2-byte unsigned integer (in network byte order: Big Endian)
-///   representing a status code with value /code/ defined in [Section 7.4](https:///datatracker.ietf.org/doc/html/rfc6455#section-7.4).
-///   Following the 2-byte integer,
-///
-/// - The application MUST NOT send any more data frames after sending a `Close` frame.
+/// Error returned by [WebSocket::reunite], When the halves are not from the same websocket.
 ///
-/// - If an endpoint receives a Close frame and did not previously send a
-///   Close frame, the endpoint MUST send a Close frame in response.  (When
-///   sending a Close frame in response, the endpoint typically echos the
-///   status code it received.)  It SHOULD do so as soon as practical.  An
-///   endpoint MAY delay sending a Close frame until its current message is
-///   sent
-///
-/// - After both sending and receiving a Close message, an endpoint
-///   considers the WebSocket connection closed and MUST close the
-///   underlying TCP connection.
-fn on_close(msg: &[u8]) -> Event {
-    let code = msg
-        .get(..2)
-        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
-        .unwrap_or(1000);
-
-    match code {
-        1000..=1003 | 1007..=1011 | 1015 | 3000..=3999 | 4000..=4999 => {
-            match msg.get(2..).map(|data| String::from_utf8(data.to_vec())) {
-                Some(Ok(msg)) => Event::Close {
-                    code,
-                    reason: msg.into_boxed_str(),
-                },
-                None => Event::Close {
-                    code,
-                    reason: "".into(),
-                },
-                Some(Err(_)) => Event::Error("invalid utf-8 payload"),
-            }
-        }
-        _ => Event::Error("invalid close code"),
+/// It contains the original halves.
+pub struct ReuniteError<IO>(pub WsReader<IO>, pub WsWriter<IO>);
+
+impl<IO> fmt::Debug for ReuniteError<IO> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ReuniteError").finish()
     }
 }
 
-impl<IO> From<(IO, Role)> for WebSocket<IO> {
-    #[inline]
-    fn from((stream, role): (IO, Role)) -> Self {
-        Self {
-            stream,
-            max_payload_len: 16 * 1024 * 1024,
-            role,
-            is_closed: false,
-            fragment: None,
-        }
+impl<IO> fmt::Display for ReuniteError<IO> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("tried to reunite halves that are not from the same websocket")
     }
 }
+
+impl<IO> std::error::Error for ReuniteError<IO> {}
@@ -1,3 +1,4 @@
+#![cfg(feature = "tokio")]
 use std::io;
 use web_socket::*;
 
@@ -0,0 +1,176 @@
+#![cfg(feature = "tokio")]
+mod common;
+
+use common::Stream;
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{duplex, AsyncWrite},
+    time::{sleep, timeout},
+};
+use web_socket::{CloseCode, Event, ReadOnly, WebSocket};
+
+/// Records every write to the underlying stream.
+#[derive(Default)]
+struct Recorder {
+    data: Vec<u8>,
+    writes: usize,
+}
+
+impl AsyncWrite for Recorder {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.writes += 1;
+        self.data.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+async fn recv_all(data: &[u8]) -> io::Result<Vec<Event>> {
+    let mut ws = WebSocket::client(ReadOnly(data));
+    let mut events = vec![];
+    while ws.buffered().len() + ws.stream.0.len() > 0 {
+        events.push(ws.recv().await?);
+    }
+    Ok(events)
+}
+
+#[tokio::test]
+async fn write_buffer_size() -> io::Result<()> {
+    let mut ws = WebSocket::server(Recorder::default());
+    ws.write_buffer_size = 100;
+    // Each frame is 7 bytes
+    for _ in 0..14 {
+        ws.send("Hello").await?;
+    }
+    assert_eq!(ws.stream.writes, 0);
+    ws.send("Hello").await?;
+    assert_eq!(ws.stream.writes, 1);
+    assert_eq!(ws.stream.data.len(), 105);
+
+    ws.send("World").await?;
+    assert_eq!(ws.stream.writes, 1);
+    ws.flush().await?;
+    assert_eq!(ws.stream.writes, 2);
+
+    let events = recv_all(&ws.stream.data).await?;
+    assert_eq!(events.len(), 16);
+    assert!(matches!(&events[15], Event::Data { data, .. } if &**data == b"World"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn control_frames() -> io::Result<()> {
+    let mut ws = WebSocket::server(Recorder::default());
+    ws.write_buffer_size = 1024;
+    ws.send("Hello").await?;
+    ws.send_pong("pong").await?;
+    ws.send("World").await?;
+    ws.send_close(CloseCode::Normal).await?;
+
+    // `Pong` jumps the queue, `Close` is written after the queued frames.
+    let events = recv_all(&ws.stream.data).await?;
+    assert!(matches!(&events[0], Event::Pong(data) if &**data == b"pong"));
+    assert!(matches!(&events[1], Event::Data { data, .. } if &**data == b"Hello"));
+    assert!(matches!(&events[2], Event::Data { data, .. } if &**data == b"World"));
+    assert!(matches!(&events[3], Event::Close { code: 1000, .. }));
+    Ok(())
+}
+
+#[tokio::test]
+async fn send_batch() -> io::Result<()> {
+    let mut ws = WebSocket::client(Recorder::default());
+    ws.send_batch(["Hello", "World", "!"]).await?;
+    assert_eq!(ws.stream.writes, 1);
+
+    let mut server = WebSocket::server(ReadOnly(&ws.stream.data[..]));
+    for msg in ["Hello", "World", "!"] {
+        assert!(
+            matches!(server.recv().await?, Event::Data { data, .. } if *data == *msg.as_bytes())
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn write_delay() -> io::Result<()> {
+    let mut ws = WebSocket::server(Recorder::default());
+    ws.write_buffer_size = 1024;
+    ws.write_delay = Some(Duration::from_millis(20));
+
+    ws.send("Hello").await?;
+    assert!(ws.flush_deadline().is_some());
+    ws.flush_delayed().await?;
+    assert_eq!(ws.stream.writes, 1);
+    assert!(ws.flush_deadline().is_none());
+
+    // Due frames are also written by the next `send`.
+    ws.send("Hello").await?;
+    sleep(Duration::from_millis(30)).await;
+    ws.send("World").await?;
+    assert_eq!(ws.stream.writes, 2);
+    assert_eq!(recv_all(&ws.stream.data).await?.len(), 3);
+    Ok(())
+}
+
+#[tokio::test]
+async fn write_delay_while_receiving() -> io::Result<()> {
+    let (client, server) = duplex(1024);
+    let mut server = WebSocket::server(server);
+    server.write_buffer_size = 1024;
+    server.write_delay = Some(Duration::from_millis(20));
+    server.send("Hello").await?;
+
+    let client = tokio::spawn(async move {
+        let mut client = WebSocket::client(client);
+        let event = client.recv().await?;
+        client.close(()).await?;
+        io::Result::Ok(event)
+    });
+    // Nothing else is sent, The queued frame is written once it is due, While `recv` is pending.
+    let event = timeout(Duration::from_secs(1), server.recv()).await??;
+    assert!(matches!(event, Event::Close { .. }));
+    assert!(matches!(client.await??, Event::Data { data, .. } if *data == *b"Hello"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn write_delay_error() -> io::Result<()> {
+    let mut ws = WebSocket::server(Stream::new(common::frame(
+        1,
+        true,
+        b"Hi",
+        Some([1, 2, 3, 4]),
+    )));
+    ws.write_buffer_size = 1024;
+    ws.write_delay = Some(Duration::from_millis(1));
+    ws.send("Hello").await?;
+    sleep(Duration::from_millis(5)).await;
+
+    // The due frame is written while `recv` is pending, But the write fails.
+    ws.stream.write_error = Some(io::ErrorKind::BrokenPipe);
+    assert_eq!(ws.recv().await?.as_text(), Some("Hi"));
+    assert!(ws.stream.output.is_empty());
+
+    // The error is returned by the next write, And the queued frame is still written by a flush.
+    let err = ws.send("World").await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    ws.flush().await?;
+    assert_eq!(ws.stream.output, b"\x81\x05Hello");
+    Ok(())
+}
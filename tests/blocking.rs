@@ -0,0 +1,88 @@
+#![cfg(feature = "std")]
+mod common;
+
+use common::Stream;
+use std::{
+    io::{self, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+use web_socket::{blocking::WebSocket, CloseCode, CloseState, Event, Frame};
+
+/// Returns connected server and client streams.
+fn pair() -> io::Result<(TcpStream, TcpStream)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let client = TcpStream::connect(listener.local_addr()?)?;
+    let (server, _) = listener.accept()?;
+    Ok((server, client))
+}
+
+#[test]
+fn echo() -> io::Result<()> {
+    let (server, client) = pair()?;
+    let server = thread::spawn(move || -> io::Result<()> {
+        let mut ws = WebSocket::server(server);
+        loop {
+            match ws.recv()? {
+                Event::Data { data, .. } => ws.send(&*data)?,
+                Event::Ping(data) => ws.send_pong(data)?,
+                Event::Close { .. } => return ws.close(()),
+                ev => panic!("unexpected event: {ev:?}"),
+            }
+        }
+    });
+
+    let mut ws = WebSocket::client(client);
+    ws.send("Hello")?;
+    assert!(matches!(ws.recv()?, Event::Data { data, .. } if &*data == b"Hello"));
+    ws.send(&[0; 100000][..])?;
+    assert!(matches!(ws.recv()?, Event::Data { data, .. } if data.len() == 100000));
+    ws.send_ping("ping")?;
+    assert!(matches!(ws.recv()?, Event::Pong(data) if &*data == b"ping"));
+
+    ws.send_close(CloseCode::Normal)?;
+    assert!(matches!(ws.recv_event()?, Event::Close { code: 1000, .. }));
+    assert_eq!(ws.close_state(), CloseState::Closed);
+    server.join().unwrap()
+}
+
+#[test]
+fn resume_after_timeout() -> io::Result<()> {
+    let (mut server, client) = pair()?;
+    client.set_read_timeout(Some(Duration::from_millis(10)))?;
+    let mut ws = WebSocket::client(client);
+
+    let frame = Frame::from("Hello, World!").encode_without_mask();
+    server.write_all(&frame[..5])?;
+    let err = ws.recv().unwrap_err();
+    assert!(matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    ));
+    assert!(!ws.is_closed());
+
+    server.write_all(&frame[5..])?;
+    assert_eq!(ws.recv()?.as_text(), Some("Hello, World!"));
+    Ok(())
+}
+
+#[test]
+fn send_after_close() -> io::Result<()> {
+    let mut ws = WebSocket::client(vec![]);
+    ws.send_close(())?;
+    let err = ws.send("Hello").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+    assert_eq!(ws.stream[0], 0x88);
+    assert_eq!(ws.stream.len(), 2 + 4);
+    Ok(())
+}
+
+#[test]
+fn close_on_recv_error() {
+    let mut stream = Stream::default();
+    let ws = WebSocket::server(&mut stream);
+    let err = ws.close(()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(stream.output, [0x88, 0]);
+}
@@ -0,0 +1,111 @@
+#![cfg(feature = "tokio")]
+use std::{io, time::Duration};
+use tokio::{
+    io::{duplex, AsyncWriteExt},
+    time::timeout,
+};
+use web_socket::{Event, MemoryBudget, ProtocolError, ReadOnly, WebSocket};
+
+mod common;
+
+#[tokio::test]
+async fn lazy_payload_allocation() -> io::Result<()> {
+    let (mut client, server) = duplex(1024);
+    let budget = MemoryBudget::new(usize::MAX);
+    let mut ws = WebSocket::client(server);
+    ws.memory_budget = Some(budget.clone());
+
+    // Header of 16 MB frame, Followed by just a few bytes.
+    client
+        .write_all(&[0x82, 127, 0, 0, 0, 0, 1, 0, 0, 0])
+        .await?;
+    client.write_all(b"Hello").await?;
+    assert!(timeout(Duration::from_millis(10), ws.recv()).await.is_err());
+    assert!(budget.used() <= 4096);
+
+    drop(ws);
+    assert_eq!(budget.used(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn budget_exceeded() -> io::Result<()> {
+    let global = MemoryBudget::new(10000);
+    let budget = MemoryBudget::with_parent(6000, &global);
+    let data = [0; 8000];
+
+    let frame = common::frame(2, true, &data, None);
+    let mut ws = WebSocket::client(ReadOnly(&frame[..]));
+    ws.memory_budget = Some(budget.clone());
+    assert!(matches!(
+        ws.recv().await?,
+        Event::Error(ProtocolError::MessageTooLarge)
+    ));
+    assert_eq!(budget.used(), 0);
+    assert_eq!(global.used(), 0);
+
+    let frame = common::frame(2, true, &data[..5000], None);
+    let mut ws = WebSocket::client(ReadOnly(&frame[..]));
+    ws.memory_budget = Some(budget.clone());
+    assert!(matches!(ws.recv().await?, Event::Data { data, .. } if data.len() == 5000));
+    assert_eq!(global.used(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn shared_budget() -> io::Result<()> {
+    let global = MemoryBudget::new(6000);
+    let (mut a, server_a) = duplex(8192);
+    let (mut b, server_b) = duplex(8192);
+    let mut ws_a = WebSocket::client(server_a);
+    let mut ws_b = WebSocket::client(server_b);
+    ws_a.memory_budget = Some(MemoryBudget::with_parent(5000, &global));
+    ws_b.memory_budget = Some(MemoryBudget::with_parent(5000, &global));
+
+    let frame = common::frame(2, true, &[0; 5000], None);
+    a.write_all(&frame[..4000]).await?;
+    assert!(timeout(Duration::from_millis(10), ws_a.recv())
+        .await
+        .is_err());
+    assert!(global.used() >= 4000);
+
+    // Partial frame of `ws_a` holds most of the global budget.
+    b.write_all(&frame).await?;
+    assert!(matches!(
+        ws_b.recv().await?,
+        Event::Error(ProtocolError::MessageTooLarge)
+    ));
+
+    a.write_all(&frame[4000..]).await?;
+    assert!(matches!(ws_a.recv().await?, Event::Data { data, .. } if data.len() == 5000));
+    assert_eq!(global.used(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn reassembled_message_budget() -> io::Result<()> {
+    let budget = MemoryBudget::new(5000);
+    let frames = [
+        common::frame(2, false, &[0; 2000], None),
+        common::frame(0, false, &[0; 2000], None),
+        common::frame(0, true, &[0; 2000], None),
+    ]
+    .concat();
+
+    let mut ws = WebSocket::client(ReadOnly(&frames[..]));
+    ws.memory_budget = Some(budget.clone());
+    assert!(matches!(
+        ws.recv_message().await?,
+        Event::Error(ProtocolError::MessageTooLarge)
+    ));
+    assert_eq!(budget.used(), 0);
+
+    // Fragments that are returned by `recv`, Are not held by the websocket.
+    let mut ws = WebSocket::client(ReadOnly(&frames[..]));
+    ws.memory_budget = Some(budget.clone());
+    for _ in 0..3 {
+        assert!(matches!(ws.recv().await?, Event::Data { .. }));
+    }
+    assert_eq!(budget.used(), 0);
+    Ok(())
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "tokio")]
+use std::{
+    future::{poll_fn, Future},
+    io,
+    pin::{pin, Pin},
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{duplex, AsyncRead, AsyncWriteExt, ReadBuf},
+    select,
+    time::{interval, sleep, timeout},
+};
+use web_socket::{DataType, Event, Frame, MessageType, ReadOnly, WebSocket};
+
+/// Reads one byte at a time, And returns `Pending` in between.
+///
+/// It never completes once all the data is read.
+struct Trickle {
+    data: Vec<u8>,
+    pos: usize,
+    pending: bool,
+}
+
+impl AsyncRead for Trickle {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.pending = !self.pending;
+        if self.pending {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        match self.data.get(self.pos) {
+            Some(&byte) => {
+                buf.put_slice(&[byte]);
+                self.pos += 1;
+                Poll::Ready(Ok(()))
+            }
+            None => Poll::Pending,
+        }
+    }
+}
+
+#[tokio::test]
+async fn resume_after_cancel() -> io::Result<()> {
+    let long = "x".repeat(300);
+    let frames = [
+        Frame::from("Hello").encode_with([1, 2, 3, 4]),
+        Frame::from(long.as_str()).encode_with([5, 6, 7, 8]),
+        Frame {
+            fin: true,
+            opcode: 9,
+            data: b"ping",
+        }
+        .encode_with([9, 10, 11, 12]),
+    ];
+    let mut ws = WebSocket::server(ReadOnly(Trickle {
+        data: frames.concat(),
+        pos: 0,
+        pending: false,
+    }));
+
+    let mut events = vec![];
+    let mut cancelled = 0;
+    while events.len() < 3 {
+        // `recv` future is polled once, And dropped whenever the reader returns `Pending`.
+        let mut recv = pin!(ws.recv());
+        match poll_fn(|cx| Poll::Ready(recv.as_mut().poll(cx))).await {
+            Poll::Ready(event) => events.push(event?),
+            Poll::Pending => cancelled += 1,
+        }
+    }
+    assert!(cancelled > 300);
+    assert!(matches!(
+        &events[0],
+        Event::Data { ty: DataType::Complete(MessageType::Text), data, .. } if &**data == b"Hello"
+    ));
+    assert!(matches!(&events[1], Event::Data { data, .. } if **data == *long.as_bytes()));
+    assert!(matches!(&events[2], Event::Ping(data) if &**data == b"ping"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn recv_in_select() -> io::Result<()> {
+    let (mut client, server) = duplex(64);
+    let frame = Frame::from("Hello, World!").encode_without_mask();
+    tokio::spawn(async move {
+        for chunk in frame.chunks(3) {
+            client.write_all(chunk).await?;
+            sleep(Duration::from_millis(5)).await;
+        }
+        io::Result::Ok(client)
+    });
+
+    let mut ws = WebSocket::client(server);
+    let mut ticker = interval(Duration::from_millis(1));
+    let mut ticks = 0;
+    let event = loop {
+        select! {
+            event = ws.recv() => break event?,
+            _ = ticker.tick() => ticks += 1,
+        }
+    };
+    assert!(ticks > 0);
+    assert!(matches!(event, Event::Data { data, .. } if &*data == b"Hello, World!"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn partial_frame_into_parts() -> io::Result<()> {
+    let data = [0x81, 5, b'H', b'e'];
+    let mut ws = WebSocket::client(ReadOnly(Trickle {
+        data: data.to_vec(),
+        pos: 0,
+        pending: false,
+    }));
+    assert!(timeout(Duration::from_millis(10), ws.recv()).await.is_err());
+    let (_, buffered) = ws.into_parts();
+    assert_eq!(buffered, data);
+    Ok(())
+}
@@ -0,0 +1,115 @@
+#![cfg(feature = "tokio")]
+mod common;
+
+use common::Stream;
+use std::{io, time::Duration};
+use tokio::io::{duplex, AsyncReadExt};
+use web_socket::{CloseCode, CloseState, Event, WebSocket};
+
+#[tokio::test]
+async fn close_handshake() -> io::Result<()> {
+    let (client, server) = duplex(1024);
+    let server = tokio::spawn(async move {
+        let mut ws = WebSocket::server(server);
+        // The status code of the received `Close` frame is echoed, Before the event is returned.
+        let event = ws.recv().await?;
+        assert_eq!(ws.close_state(), CloseState::Closed);
+        ws.close(()).await?;
+        io::Result::Ok(event)
+    });
+
+    let mut ws = WebSocket::client(client);
+    ws.send_close(CloseCode::Away).await?;
+    assert_eq!(ws.close_state(), CloseState::CloseSent);
+    assert!(matches!(ws.recv().await?, Event::Close { code: 1001, .. }));
+    assert_eq!(ws.close_state(), CloseState::Closed);
+    assert!(matches!(server.await??, Event::Close { code: 1001, .. }));
+    Ok(())
+}
+
+#[tokio::test]
+async fn close_waits_for_reply() -> io::Result<()> {
+    let (client, server) = duplex(1024);
+    let server = tokio::spawn(async move {
+        let mut ws = WebSocket::server(server);
+        ws.send("pending").await?;
+        assert!(matches!(ws.recv().await?, Event::Close { code: 1000, .. }));
+        io::Result::Ok(ws.close_state())
+    });
+
+    // data frames that are received during the closing handshake are discarded.
+    WebSocket::client(client).close(CloseCode::Normal).await?;
+    assert_eq!(server.await??, CloseState::Closed);
+    Ok(())
+}
+
+#[tokio::test]
+async fn close_timeout() -> io::Result<()> {
+    let (client, mut server) = duplex(1024);
+    let mut ws = WebSocket::client(client);
+    ws.close_timeout = Duration::from_millis(10);
+
+    let err = ws.close("bye").await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+    // Close frame is sent, then the stream is shutdown.
+    let mut buf = vec![];
+    server.read_to_end(&mut buf).await?;
+    // status code (1000) and "bye"
+    assert_eq!(buf.len(), 2 + 4 + 5);
+    assert_eq!(buf[..2], [0x88, 0x80 | 5]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn send_after_close() -> io::Result<()> {
+    let (client, server) = duplex(1024);
+    let mut client = WebSocket::client(client);
+    let mut server = WebSocket::server(server);
+
+    client.send_close(CloseCode::Normal).await?;
+    let err = client.send("Hello").await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+
+    assert!(matches!(server.recv().await?, Event::Close { .. }));
+    assert!(server.recv().await.is_err());
+    // The pending reply is sent, before the data frame is rejected.
+    assert!(server.send_ping("ping").await.is_err());
+    assert_eq!(server.close_state(), CloseState::Closed);
+
+    assert!(matches!(
+        client.recv().await?,
+        Event::Close { code: 1000, .. }
+    ));
+    assert_eq!(client.close_state(), CloseState::Closed);
+    Ok(())
+}
+
+#[tokio::test]
+async fn protocol_error() -> io::Result<()> {
+    let (client, mut server) = duplex(1024);
+    let mut ws = WebSocket::client(client);
+    // masked frame, sent by the server.
+    tokio::io::AsyncWriteExt::write_all(&mut server, &[0x81, 0x80, 0, 0, 0, 0]).await?;
+
+    assert!(matches!(ws.recv().await?, Event::Error(_)));
+    assert_eq!(ws.close_state(), CloseState::Closed);
+
+    let mut buf = [0; 8];
+    server.read_exact(&mut buf).await?;
+    assert_eq!(buf[..2], [0x88, 0x82]);
+    let code = [buf[2] ^ buf[6], buf[3] ^ buf[7]];
+    assert_eq!(u16::from_be_bytes(code), 1002);
+    Ok(())
+}
+
+#[tokio::test]
+async fn close_on_recv_error() {
+    // The remote endpoint closes the stream, Without replying.
+    let mut stream = Stream::default();
+    let ws = WebSocket::server(&mut stream);
+    let err = ws.close(()).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(stream.output, [0x88, 0]);
+    assert!(stream.shutdown);
+}
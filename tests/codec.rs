@@ -0,0 +1,183 @@
+#![cfg(feature = "tokio-util")]
+use bytes::BytesMut;
+use std::io;
+use tokio_util::codec::{Decoder, Encoder};
+use web_socket::{
+    codec::{Frame, WebSocketCodec},
+    ProtocolError,
+};
+
+fn encode(codec: &mut WebSocketCodec, frame: impl Into<Frame>) -> BytesMut {
+    let mut buf = BytesMut::new();
+    codec.encode(frame.into(), &mut buf).unwrap();
+    buf
+}
+
+fn protocol_error(err: io::Error) -> ProtocolError {
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    *err.into_inner().unwrap().downcast().unwrap()
+}
+
+#[test]
+fn roundtrip() {
+    let mut client = WebSocketCodec::client();
+    let mut server = WebSocketCodec::server();
+    let long = "x".repeat(70_000);
+    for text in ["Hello", &long[..200], &long] {
+        // Client frames are masked.
+        let mut buf = encode(&mut client, text);
+        assert_eq!(buf[1] & 0x80, 0x80);
+        let frame = server.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(frame, Frame::from(text));
+        assert!(buf.is_empty());
+
+        let mut buf = encode(&mut server, text);
+        assert_eq!(
+            &buf[..],
+            web_socket::Frame::from(text).encode_without_mask()
+        );
+        let frame = client.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(frame, Frame::from(text));
+    }
+}
+
+#[test]
+fn partial_frame() {
+    let mut codec = WebSocketCodec::server();
+    let bytes = encode(&mut WebSocketCodec::client(), &[7; 300][..]);
+
+    let mut buf = BytesMut::new();
+    for &byte in &bytes[..bytes.len() - 1] {
+        buf.extend_from_slice(&[byte]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+    }
+    buf.extend_from_slice(&bytes[bytes.len() - 1..]);
+    let frame = codec.decode(&mut buf).unwrap().unwrap();
+    assert_eq!(&frame.data[..], [7; 300]);
+}
+
+#[test]
+fn fragments() {
+    let mut client = WebSocketCodec::client();
+    let mut buf = BytesMut::new();
+    for (fin, opcode, data) in [(false, 1, "Hello"), (true, 9, ""), (true, 0, ", World")] {
+        let frame = Frame {
+            fin,
+            opcode,
+            data: data.into(),
+        };
+        client.encode(frame, &mut buf).unwrap();
+    }
+    let mut server = WebSocketCodec::server();
+    let mut frames = vec![];
+    while let Some(frame) = server.decode(&mut buf).unwrap() {
+        frames.push((frame.fin, frame.opcode));
+    }
+    assert_eq!(frames, [(false, 1), (true, 9), (true, 0)]);
+
+    // A continuation frame without a started message.
+    let mut buf = encode(&mut client, Frame::new(0, "Hello"));
+    let err = server.decode(&mut buf).unwrap_err();
+    assert_eq!(protocol_error(err), ProtocolError::InvalidContinuation);
+}
+
+#[test]
+fn protocol_errors() {
+    let mut server = WebSocketCodec::server();
+    let mut client = WebSocketCodec::client();
+
+    // Frames sent by the server are not masked.
+    let mut buf = encode(&mut WebSocketCodec::server(), "Hello");
+    let err = server.decode(&mut buf).unwrap_err();
+    assert_eq!(protocol_error(err), ProtocolError::UnmaskedFrame);
+
+    let mut buf = encode(&mut WebSocketCodec::client(), "Hello");
+    let err = client.decode(&mut buf).unwrap_err();
+    assert_eq!(protocol_error(err), ProtocolError::MaskedFrame);
+
+    let mut buf = BytesMut::from(&b"\x89\x7e\x00\x7e"[..]);
+    let err = client.decode(&mut buf).unwrap_err();
+    assert_eq!(protocol_error(err), ProtocolError::ControlFrameTooLarge);
+
+    let mut buf = BytesMut::from(&b"\x8b\x00"[..]);
+    let err = client.decode(&mut buf).unwrap_err();
+    assert_eq!(protocol_error(err), ProtocolError::UnknownOpcode);
+
+    let mut buf = BytesMut::from(&b"\x83\x00"[..]);
+    let err = client.decode(&mut buf).unwrap_err();
+    assert_eq!(protocol_error(err), ProtocolError::UnknownOpcode);
+
+    let mut buf = BytesMut::from(&b"\xc1\x00"[..]);
+    let err = client.decode(&mut buf).unwrap_err();
+    assert_eq!(protocol_error(err), ProtocolError::ReservedBits);
+
+    // Length is checked before the payload is received.
+    let mut client = WebSocketCodec::client();
+    client.max_payload_len = 100;
+    let mut buf = BytesMut::from(&b"\x82\x7e\x00\x7e"[..]);
+    let err = client.decode(&mut buf).unwrap_err();
+    assert_eq!(protocol_error(err), ProtocolError::PayloadTooLarge);
+
+    client.strict_length_encoding = true;
+    let mut buf = BytesMut::from(&b"\x82\x7e\x00\x05"[..]);
+    let err = client.decode(&mut buf).unwrap_err();
+    assert_eq!(protocol_error(err), ProtocolError::NonMinimalLength);
+}
+
+#[test]
+fn encode_errors() {
+    let mut codec = WebSocketCodec::client();
+    let cases = [
+        (
+            Frame {
+                fin: false,
+                opcode: 9,
+                data: "".into(),
+            },
+            ProtocolError::FragmentedControlFrame,
+        ),
+        (
+            Frame::new(10, vec![0; 126]),
+            ProtocolError::ControlFrameTooLarge,
+        ),
+        (Frame::new(3, "Hello"), ProtocolError::UnknownOpcode),
+        (Frame::new(11, ""), ProtocolError::UnknownOpcode),
+        (Frame::new(16, ""), ProtocolError::UnknownOpcode),
+    ];
+    for (frame, expected) in cases {
+        let mut buf = BytesMut::new();
+        let err = codec.encode(frame, &mut buf).unwrap_err();
+        assert_eq!(protocol_error(err), expected);
+        assert!(buf.is_empty());
+    }
+    // Largest control frame.
+    let mut buf = encode(&mut codec, Frame::new(8, vec![0; 125]));
+    let frame = WebSocketCodec::server().decode(&mut buf).unwrap().unwrap();
+    assert_eq!(frame.data.len(), 125);
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn framed() -> io::Result<()> {
+    use futures::{SinkExt, StreamExt};
+    use tokio::io::duplex;
+    use tokio_util::codec::Framed;
+    use web_socket::{Event, WebSocket};
+
+    let (client, server) = duplex(1024);
+    let mut client = WebSocket::client(client);
+    let mut server = Framed::new(server, WebSocketCodec::server());
+
+    client.send("Hello").await?;
+    client.send_ping("ping").await?;
+    let frame = server.next().await.unwrap()?;
+    assert_eq!(frame, Frame::from("Hello"));
+    let frame = server.next().await.unwrap()?;
+    assert_eq!(frame, Frame::new(9, "ping"));
+
+    server.send(Frame::new(10, frame.data)).await?;
+    server.send(Frame::from(&b"World"[..])).await?;
+    assert!(matches!(client.recv().await?, Event::Pong(data) if &*data == b"ping"));
+    assert!(matches!(client.recv().await?, Event::Data { data, .. } if &*data == b"World"));
+    Ok(())
+}
@@ -0,0 +1,268 @@
+//! Helpers that are shared by integration tests.
+#![allow(dead_code)]
+
+use web_socket::Frame;
+
+/// Encode a raw frame, Its payload is masked with `mask` (if any).
+///
+/// Frames are built by hand, So that tests can send fragments and frames that a websocket would never send.
+pub fn frame(opcode: u8, fin: bool, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let frame = Frame {
+        fin,
+        opcode,
+        data: payload,
+    };
+    match mask {
+        Some(mask) => frame.encode_with(mask),
+        None => frame.encode_without_mask(),
+    }
+}
+
+/// In-memory stream, That reads from `input` and writes into `output`.
+///
+/// It implements the stream traits of every backend, To test how each backend resumes.
+#[derive(Debug, Default)]
+pub struct Stream {
+    pub input: Vec<u8>,
+    pub pos: usize,
+    pub output: Vec<u8>,
+    /// Read at most one byte at a time.
+    pub trickle: bool,
+    /// Write at most this many bytes at a time.
+    pub write_limit: Option<usize>,
+    /// Every read is pending once, Before it is ready.
+    pub pending_reads: bool,
+    /// Every write is pending once, Before it is ready.
+    pub pending_writes: bool,
+    /// Reads fail with `ConnectionReset`.
+    pub reset: bool,
+    /// The next write fails with this error.
+    pub write_error: Option<std::io::ErrorKind>,
+    /// The stream was shut down (or closed).
+    pub shutdown: bool,
+    /// Whether the pending read (or write) was already polled.
+    polled: bool,
+}
+
+impl Stream {
+    pub fn new(input: Vec<u8>) -> Self {
+        Self {
+            input,
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true`, If this poll is pending. So every other poll is ready.
+    fn is_pending(&mut self, pending: bool) -> bool {
+        self.polled = pending && !self.polled;
+        self.polled
+    }
+
+    fn read_into(&mut self, buf: &mut [u8]) -> usize {
+        let rest = &self.input[self.pos..];
+        let mut len = buf.len().min(rest.len());
+        if self.trickle {
+            len = len.min(1);
+        }
+        buf[..len].copy_from_slice(&rest[..len]);
+        self.pos += len;
+        len
+    }
+
+    fn write_from(&mut self, buf: &[u8]) -> usize {
+        let len = buf.len().min(self.write_limit.unwrap_or(usize::MAX));
+        self.output.extend_from_slice(&buf[..len]);
+        len
+    }
+}
+
+#[cfg(feature = "std")]
+mod blocking {
+    use super::Stream;
+    use std::io::{self, Read, Write};
+
+    impl Read for Stream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.reset {
+                return Err(io::ErrorKind::ConnectionReset.into());
+            }
+            Ok(self.read_into(buf))
+        }
+    }
+
+    impl Write for Stream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if let Some(kind) = self.write_error.take() {
+                return Err(kind.into());
+            }
+            Ok(self.write_from(buf))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod tokio {
+    use super::Stream;
+    use std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+    use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+    impl AsyncRead for Stream {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let this = self.get_mut();
+            if this.is_pending(this.pending_reads) {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if this.reset {
+                return Poll::Ready(Err(io::ErrorKind::ConnectionReset.into()));
+            }
+            let amt = this.read_into(buf.initialize_unfilled());
+            buf.advance(amt);
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    impl AsyncWrite for Stream {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let this = self.get_mut();
+            if this.is_pending(this.pending_writes) {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if let Some(kind) = this.write_error.take() {
+                return Poll::Ready(Err(kind.into()));
+            }
+            Poll::Ready(Ok(this.write_from(buf)))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.get_mut().shutdown = true;
+            Poll::Ready(Ok(()))
+        }
+    }
+}
+
+#[cfg(feature = "futures-io")]
+mod futures_io {
+    use super::Stream;
+    use futures::io::{AsyncRead, AsyncWrite};
+    use std::{
+        io,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    impl AsyncRead for Stream {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let this = self.get_mut();
+            if this.is_pending(this.pending_reads) {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if this.reset {
+                return Poll::Ready(Err(io::ErrorKind::ConnectionReset.into()));
+            }
+            Poll::Ready(Ok(this.read_into(buf)))
+        }
+    }
+
+    impl AsyncWrite for Stream {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            let this = self.get_mut();
+            if this.is_pending(this.pending_writes) {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            if let Some(kind) = this.write_error.take() {
+                return Poll::Ready(Err(kind.into()));
+            }
+            Poll::Ready(Ok(this.write_from(buf)))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.get_mut().shutdown = true;
+            Poll::Ready(Ok(()))
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io-async")]
+mod embedded {
+    use super::Stream;
+    use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+    use std::{
+        future::poll_fn,
+        task::{Context, Poll},
+    };
+
+    impl Stream {
+        /// Yields to the executor once, If `pending` is set.
+        async fn yield_now(&mut self, pending: bool) {
+            poll_fn(|cx: &mut Context| match self.is_pending(pending) {
+                true => {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+                false => Poll::Ready(()),
+            })
+            .await
+        }
+    }
+
+    impl ErrorType for Stream {
+        type Error = ErrorKind;
+    }
+
+    impl Read for Stream {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+            self.yield_now(self.pending_reads).await;
+            if self.reset {
+                return Err(ErrorKind::ConnectionReset);
+            }
+            Ok(self.read_into(buf))
+        }
+    }
+
+    impl Write for Stream {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+            self.yield_now(self.pending_writes).await;
+            Ok(self.write_from(buf))
+        }
+
+        async fn flush(&mut self) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+    }
+}
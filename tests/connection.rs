@@ -0,0 +1,143 @@
+#![cfg(feature = "std")]
+use std::io;
+use web_socket::{CloseCode, CloseState, Connection, DataType, Event, Frame, ProtocolError, Role};
+
+/// Move the outgoing bytes of `from` into `to`.
+fn transfer(from: &mut Connection, to: &mut Connection) {
+    to.receive(from.outgoing());
+    from.consume_outgoing(from.outgoing().len());
+}
+
+#[test]
+fn byte_at_a_time() -> io::Result<()> {
+    let mut client = Connection::new(Role::Client);
+    let mut server = Connection::new(Role::Server);
+    client.encode("Hello")?;
+    client.encode(&[0xab; 70000][..])?;
+    client.encode_ping("ping")?;
+    let bytes = client.outgoing().to_vec();
+
+    let mut events = vec![];
+    for byte in bytes {
+        server.receive(&[byte]);
+        while let Some(event) = server.next_event()? {
+            events.push(event);
+        }
+    }
+    assert_eq!(events.len(), 3);
+    assert!(matches!(&events[0], Event::Data { data, .. } if &**data == b"Hello"));
+    assert!(
+        matches!(&events[1], Event::Data { data, .. } if data.iter().all(|&b| b == 0xab) && data.len() == 70000)
+    );
+    assert!(matches!(&events[2], Event::Ping(data) if &**data == b"ping"));
+    Ok(())
+}
+
+#[test]
+fn read_buf() -> io::Result<()> {
+    let mut server = Connection::new(Role::Server);
+    let mut client = Connection::new(Role::Client);
+    server.read_buffer_size = 1024;
+    server.encode("Hello")?;
+    server.encode(&[1; 100000][..])?;
+    let mut bytes = server.outgoing();
+
+    let mut events = vec![];
+    let mut reads = 0;
+    while !bytes.is_empty() {
+        while let Some(event) = client.next_event()? {
+            events.push(event);
+        }
+        let buf = client.read_buf();
+        let amt = buf.len().min(bytes.len());
+        buf[..amt].copy_from_slice(&bytes[..amt]);
+        client.advance(amt);
+        bytes = &bytes[amt..];
+        reads += 1;
+    }
+    events.extend(client.next_event()?);
+    assert_eq!(events.len(), 2);
+    assert!(
+        matches!(&events[1], Event::Data { data, .. } if data.iter().all(|&b| b == 1) && data.len() == 100000)
+    );
+    // Large payload is read directly into the payload buffer, Not 1 KB at a time.
+    assert!(reads < 20, "reads: {reads}");
+    Ok(())
+}
+
+#[test]
+fn reassemble_message() -> io::Result<()> {
+    let mut client = Connection::new(Role::Client);
+    let mut server = Connection::new(Role::Server);
+    client.encode(Frame {
+        fin: false,
+        opcode: 1,
+        data: b"Hello, ",
+    })?;
+    client.encode_ping("ping")?;
+    client.encode(Frame {
+        fin: true,
+        opcode: 0,
+        data: b"World!",
+    })?;
+    transfer(&mut client, &mut server);
+
+    assert!(matches!(server.next_message()?, Some(Event::Ping(_))));
+    let event = server.next_message()?.unwrap();
+    assert_eq!(event.as_text(), Some("Hello, World!"));
+    assert!(matches!(
+        event,
+        Event::Data {
+            ty: DataType::Complete(_),
+            ..
+        }
+    ));
+    assert!(server.next_message()?.is_none());
+    Ok(())
+}
+
+#[test]
+fn closing_handshake() -> io::Result<()> {
+    let mut client = Connection::new(Role::Client);
+    let mut server = Connection::new(Role::Server);
+    client.encode_close((CloseCode::Away, "bye"));
+    assert_eq!(client.close_state(), CloseState::CloseSent);
+    transfer(&mut client, &mut server);
+
+    let event = server.next_event()?;
+    assert!(matches!(event, Some(Event::Close { code: 1001, ref reason }) if &**reason == "bye"));
+    assert_eq!(server.close_state(), CloseState::CloseReceived);
+    // Sending after close, Replies to the `Close` frame.
+    let err = server.encode("Hello").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+    assert_eq!(server.close_state(), CloseState::Closed);
+    transfer(&mut server, &mut client);
+
+    assert!(matches!(
+        client.next_event()?,
+        Some(Event::Close { code: 1001, .. })
+    ));
+    assert_eq!(client.close_state(), CloseState::Closed);
+    Ok(())
+}
+
+#[test]
+fn protocol_error() -> io::Result<()> {
+    let mut client = Connection::new(Role::Client);
+    // Masked frame from the server.
+    client.receive(&Frame::from("Hello").encode_with([1, 2, 3, 4]));
+    assert!(matches!(
+        client.next_event()?,
+        Some(Event::Error(ProtocolError::MaskedFrame))
+    ));
+    client.encode_close(());
+    assert_eq!(client.outgoing()[0], 0x88);
+
+    let mut server = Connection::new(Role::Server);
+    transfer(&mut client, &mut server);
+    assert!(matches!(
+        server.next_event()?,
+        Some(Event::Close { code: 1002, .. })
+    ));
+    Ok(())
+}
@@ -0,0 +1,336 @@
+#![cfg(all(feature = "deflate", feature = "tokio"))]
+use std::{io, sync::Arc};
+use tokio::io::duplex;
+use web_socket::{
+    client,
+    deflate::PerMessageDeflate,
+    extension::{Negotiator, Offer, RSV1},
+    handshake::{self, Negotiated},
+    CloseState, DataType, Event, Frame, MessageType, ProtocolError, ReadOnly, Stream, WebSocket,
+};
+
+const JSON: &str = r#"{"event":"ticker","symbol":"BTC-USD","price":"27000.00","volume":"1200.50"}"#;
+
+fn server(config: &PerMessageDeflate, offer: Offer) -> (Offer, Negotiated) {
+    let (response, ext) = config.accept(&[offer]).unwrap();
+    let negotiated = Negotiated {
+        protocol: None,
+        extensions: vec![ext],
+    };
+    (response, negotiated)
+}
+
+fn client(config: &PerMessageDeflate, response: &Offer) -> Negotiated {
+    Negotiated {
+        protocol: None,
+        extensions: vec![config.confirm(response).unwrap()],
+    }
+}
+
+async fn recv_data(ws: &mut WebSocket<ReadOnly<&[u8]>>) -> io::Result<(DataType, Box<[u8]>)> {
+    match ws.recv().await? {
+        Event::Data { ty, data, .. } => Ok((ty, data)),
+        ev => panic!("unexpected event: {ev:?}"),
+    }
+}
+
+#[tokio::test]
+async fn negotiate() -> io::Result<()> {
+    let (client, server) = duplex(4096);
+    let server = tokio::spawn(async move {
+        let config = handshake::Config {
+            extensions: vec![Arc::new(PerMessageDeflate::default())],
+            ..Default::default()
+        };
+        handshake::accept_with(server, &config).await
+    });
+    let config = client::Config {
+        extensions: vec![Arc::new(PerMessageDeflate::default())],
+        ..Default::default()
+    };
+    let mut client = client::handshake_with(client, "example.com", "/", &config).await?;
+    let mut server = server.await?.unwrap();
+    assert_eq!(client.extensions()[0].name(), "permessage-deflate");
+
+    for _ in 0..3 {
+        client.send(JSON).await?;
+        match server.recv().await? {
+            Event::Data { ty, data, .. } => {
+                assert!(matches!(ty, DataType::Complete(MessageType::Text)));
+                assert_eq!(&*data, JSON.as_bytes());
+            }
+            ev => panic!("unexpected event: {ev:?}"),
+        }
+        server.send(JSON).await?;
+        assert!(
+            matches!(client.recv().await?, Event::Data { data, .. } if &*data == JSON.as_bytes())
+        );
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn rfc7692_examples() -> io::Result<()> {
+    let config = PerMessageDeflate {
+        threshold: 0,
+        ..Default::default()
+    };
+    let (response, negotiated) = server(&config, Offer::new("permessage-deflate"));
+    assert_eq!(response.to_string(), "permessage-deflate");
+
+    let mut writer = vec![];
+    let mut ws = WebSocket::server(&mut writer).with_negotiated(negotiated);
+    ws.send("Hello").await?;
+    assert_eq!(
+        writer,
+        [0xc1, 0x07, 0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00]
+    );
+
+    // A message compressed using 1 compressed DEFLATE block, and fragmented into 2 frames.
+    let data = [
+        0x41, 0x03, 0xf2, 0x48, 0xcd, 0x80, 0x04, 0xc9, 0xc9, 0x07, 0x00,
+    ];
+    let mut ws = WebSocket::client(ReadOnly(&data[..])).with_negotiated(client(&config, &response));
+    let (ty, first) = recv_data(&mut ws).await?;
+    assert!(matches!(
+        ty,
+        DataType::Stream(Stream::Start(MessageType::Text))
+    ));
+    let (ty, last) = recv_data(&mut ws).await?;
+    assert!(matches!(
+        ty,
+        DataType::Stream(Stream::End(MessageType::Text))
+    ));
+    assert_eq!([first, last].concat(), b"Hello");
+
+    // Context takeover: the second message refers to the first one.
+    let data = [
+        0xc1, 0x07, 0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00, //
+        0xc1, 0x05, 0xf2, 0x00, 0x11, 0x00, 0x00,
+    ];
+    let mut ws = WebSocket::client(ReadOnly(&data[..])).with_negotiated(client(&config, &response));
+    assert_eq!(&*recv_data(&mut ws).await?.1, b"Hello");
+    assert_eq!(&*recv_data(&mut ws).await?.1, b"Hello");
+    Ok(())
+}
+
+#[tokio::test]
+async fn fragmented() -> io::Result<()> {
+    let config = PerMessageDeflate::default();
+    let (response, negotiated) = server(&config, Offer::new("permessage-deflate"));
+
+    let mut writer = vec![];
+    let mut ws = WebSocket::client(&mut writer).with_negotiated(client(&config, &response));
+    let fragments = [(false, 1), (false, 0), (true, 0)];
+    for (fin, opcode) in fragments {
+        ws.send_raw(Frame {
+            fin,
+            opcode,
+            data: JSON.as_bytes(),
+        })
+        .await?;
+    }
+    ws.send_ping("ping").await?;
+
+    // `RSV1` is set on the first frame only.
+    assert_eq!(writer[0], 0x01 | RSV1);
+
+    let mut ws = WebSocket::server(ReadOnly(&writer[..])).with_negotiated(negotiated);
+    let mut message = vec![];
+    for _ in fragments {
+        message.extend_from_slice(&recv_data(&mut ws).await?.1);
+    }
+    assert_eq!(message, JSON.repeat(3).as_bytes());
+    assert!(matches!(ws.recv().await?, Event::Ping(data) if &*data == b"ping"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn threshold() -> io::Result<()> {
+    let config = PerMessageDeflate {
+        threshold: 16,
+        ..Default::default()
+    };
+    let (_, negotiated) = server(&config, Offer::new("permessage-deflate"));
+    let mut ws = WebSocket::server(vec![]).with_negotiated(negotiated);
+    ws.send("Hello").await?;
+    assert_eq!(ws.stream, b"\x81\x05Hello");
+
+    ws.stream.clear();
+    let json = JSON.repeat(4);
+    ws.send(json.as_str()).await?;
+    assert_eq!(ws.stream[0], 0x81 | RSV1);
+    assert!(ws.stream.len() < json.len());
+    Ok(())
+}
+
+#[tokio::test]
+async fn no_context_takeover() -> io::Result<()> {
+    async fn send_twice(config: &PerMessageDeflate) -> io::Result<Vec<u8>> {
+        let (_, negotiated) = server(config, Offer::new("permessage-deflate"));
+        let mut writer = vec![];
+        let mut ws = WebSocket::server(&mut writer).with_negotiated(negotiated);
+        ws.send(JSON).await?;
+        ws.send(JSON).await?;
+        Ok(writer)
+    }
+    let writer = send_twice(&PerMessageDeflate::default()).await?;
+    let first = 2 + writer[1] as usize;
+    assert!(writer.len() - first < first);
+
+    let config = PerMessageDeflate {
+        server_no_context_takeover: true,
+        ..Default::default()
+    };
+    let writer = send_twice(&config).await?;
+    let (first, second) = writer.split_at(writer.len() / 2);
+    assert_eq!(first, second);
+    Ok(())
+}
+
+#[test]
+fn negotiate_params() {
+    let config = PerMessageDeflate {
+        server_no_context_takeover: true,
+        client_max_window_bits: Some(10),
+        ..Default::default()
+    };
+    let offers = config.offer();
+    assert_eq!(
+        Offer::to_header(&offers),
+        "permessage-deflate; server_no_context_takeover; client_max_window_bits=10"
+    );
+
+    let server = PerMessageDeflate {
+        client_no_context_takeover: true,
+        server_max_window_bits: Some(12),
+        client_max_window_bits: Some(9),
+        ..Default::default()
+    };
+    let (response, _) = server.accept(&offers).unwrap();
+    assert_eq!(
+        response.to_string(),
+        "permessage-deflate; server_no_context_takeover; client_no_context_takeover; server_max_window_bits=12; client_max_window_bits=9"
+    );
+    assert!(config.confirm(&response).is_ok());
+
+    // The server must not limit the client's window, unless the client supports it.
+    let (response, _) = server
+        .accept(
+            &[Offer::new("permessage-deflate").with_param("server_max_window_bits", Some("10"))],
+        )
+        .unwrap();
+    assert_eq!(
+        response.to_string(),
+        "permessage-deflate; client_no_context_takeover; server_max_window_bits=10"
+    );
+
+    // Invalid offers are declined, the next offer is accepted.
+    let default = PerMessageDeflate::default();
+    for param in [
+        ("server_max_window_bits", Some("8")),
+        ("server_max_window_bits", Some("16")),
+        ("client_max_window_bits", Some("010")),
+        ("server_no_context_takeover", Some("1")),
+        ("x-foo", None),
+    ] {
+        let offers = [
+            Offer::new("permessage-deflate").with_param(param.0, param.1),
+            Offer::new("permessage-deflate").with_param("client_no_context_takeover", None::<&str>),
+        ];
+        let (response, _) = default.accept(&offers).unwrap();
+        assert_eq!(response, offers[1], "{param:?}");
+    }
+
+    // Invalid responses
+    for param in [
+        ("server_max_window_bits", None),
+        ("server_max_window_bits", Some("14")),
+        ("client_max_window_bits", Some("8")),
+        ("client_max_window_bits", None),
+        ("x-foo", None),
+    ] {
+        let config = PerMessageDeflate {
+            server_max_window_bits: Some(12),
+            ..Default::default()
+        };
+        let response = Offer::new("permessage-deflate").with_param(param.0, param.1);
+        assert!(config.confirm(&response).is_err(), "{param:?}");
+    }
+    let response = Offer::new("permessage-deflate");
+    assert!(config.confirm(&response).is_err());
+}
+
+#[tokio::test]
+async fn invalid_compressed_frames() -> io::Result<()> {
+    let config = PerMessageDeflate::default();
+    let (response, _) = server(&config, Offer::new("permessage-deflate"));
+
+    // `RSV1` on a continuation frame.
+    let data = [
+        0x41,
+        0x03,
+        0xf2,
+        0x48,
+        0xcd,
+        0x80 | RSV1,
+        0x04,
+        0xc9,
+        0xc9,
+        0x07,
+        0x00,
+    ];
+    let mut ws = WebSocket::client(ReadOnly(&data[..])).with_negotiated(client(&config, &response));
+    recv_data(&mut ws).await?;
+    assert!(matches!(
+        ws.recv().await?,
+        Event::Error(ProtocolError::ReservedBits)
+    ));
+
+    // Corrupted compressed data.
+    let data = [0xc1, 0x02, 0xff, 0xff];
+    let mut ws = WebSocket::client(ReadOnly(&data[..])).with_negotiated(client(&config, &response));
+    assert!(matches!(
+        ws.recv().await?,
+        Event::Error(ProtocolError::InvalidCompressedData)
+    ));
+    assert_eq!(ws.close_state(), CloseState::CloseReceived);
+
+    // Decompressed payload exceeds the limit.
+    let config = PerMessageDeflate {
+        max_decompressed_len: 4,
+        ..Default::default()
+    };
+    let data = [0xc1, 0x07, 0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00];
+    let mut ws = WebSocket::client(ReadOnly(&data[..])).with_negotiated(client(&config, &response));
+    assert!(matches!(
+        ws.recv().await?,
+        Event::Error(ProtocolError::MessageTooLarge)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+#[allow(clippy::await_holding_lock)]
+async fn split() -> io::Result<()> {
+    let config = PerMessageDeflate::default();
+    let (response, negotiated) = server(&config, Offer::new("permessage-deflate"));
+    let (client_io, server_io) = duplex(4096);
+    let mut ws = WebSocket::client(client_io).with_negotiated(client(&config, &response));
+    let (mut reader, mut writer) = WebSocket::server(server_io)
+        .with_negotiated(negotiated)
+        .split();
+
+    for _ in 0..3 {
+        ws.send(JSON).await?;
+        // Decompression state is owned by the read half, So it doesn't lock the shared extensions.
+        let extensions = writer.extensions();
+        assert!(
+            matches!(reader.recv().await?, Event::Data { data, .. } if &*data == JSON.as_bytes())
+        );
+        drop(extensions);
+        writer.send(JSON).await?;
+        assert!(matches!(ws.recv().await?, Event::Data { data, .. } if &*data == JSON.as_bytes()));
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+#![cfg(feature = "embedded-io-async")]
+mod common;
+
+use common::Stream;
+use futures::{executor::block_on, task::noop_waker_ref};
+use std::{future::Future, pin::pin, task::Context};
+use web_socket::{embedded::WebSocket, io, mask::apply_mask, CloseState, Event, Frame};
+
+#[test]
+fn partial_writes() -> io::Result<()> {
+    let mut stream = Stream::default();
+    stream.write_limit = Some(3);
+    let mut server = WebSocket::server(stream);
+    block_on(server.send("Hello, World!"))?;
+    block_on(server.send_ping("ping"))?;
+
+    let mut expected = Frame::from("Hello, World!").encode_without_mask();
+    expected.extend(
+        Frame {
+            fin: true,
+            opcode: 9,
+            data: b"ping",
+        }
+        .encode_without_mask(),
+    );
+    assert_eq!(server.stream.output, expected);
+
+    // Frames are read one byte at a time.
+    let mut stream = Stream::new(server.stream.output);
+    stream.trickle = true;
+    let mut client = WebSocket::client(stream);
+    assert_eq!(block_on(client.recv())?.as_text(), Some("Hello, World!"));
+    assert!(matches!(block_on(client.recv())?, Event::Ping(data) if &*data == b"ping"));
+    Ok(())
+}
+
+#[test]
+fn cancelled_close_reply() -> io::Result<()> {
+    let mut client = WebSocket::client(Stream::default());
+    block_on(client.send_close(()))?;
+
+    let mut stream = Stream::new(client.stream.output);
+    stream.pending_writes = true;
+    let mut server = WebSocket::server(stream);
+    // The future is dropped, While the reply is being written.
+    let mut cx = Context::from_waker(noop_waker_ref());
+    assert!(pin!(server.recv()).poll(&mut cx).is_pending());
+    assert!(server.stream.output.is_empty());
+
+    // `Close` event is kept, And returned by the next call. Once the reply is written.
+    assert!(matches!(
+        block_on(server.recv())?,
+        Event::Close { code: 1000, .. }
+    ));
+    assert_eq!(server.stream.output, [0x88, 2, 0x03, 0xe8]);
+    assert_eq!(server.close_state(), CloseState::Closed);
+    Ok(())
+}
+
+#[test]
+fn stream_error() {
+    block_on(async {
+        let mut stream = Stream::new(b"\x81".to_vec());
+        stream.reset = true;
+        let mut ws = WebSocket::client(stream);
+
+        let err = ws.recv().await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+        // Connection is unusable after a read error.
+        ws.stream.reset = false;
+        assert!(ws.recv().await.is_err());
+    })
+}
+
+#[test]
+fn encode_header() {
+    let frame = Frame::from("Hello");
+    let mut head = [0; 14];
+    assert_eq!(frame.encode_header(None, &mut head), 2);
+    assert_eq!(head[..2], [0x81, 5]);
+
+    let len = frame.encode_header(Some([1, 2, 3, 4]), &mut head);
+    let mut data = *b"Hello";
+    apply_mask(&mut data, [1, 2, 3, 4]);
+
+    let mut bytes = head[..len].to_vec();
+    bytes.extend(data);
+    assert_eq!(bytes, frame.encode_with([1, 2, 3, 4]));
+}
+
+#[test]
+fn close_on_recv_error() {
+    let mut stream = Stream::default();
+    let ws = WebSocket::server(&mut stream);
+    let err = block_on(ws.close(())).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(stream.output, [0x88, 0]);
+}
@@ -0,0 +1,177 @@
+#![cfg(feature = "tokio")]
+use std::{io, sync::Arc};
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+use web_socket::{
+    client,
+    extension::{DataFrame, Extension, Negotiator, Offer, RSV3},
+    handshake::{self, Negotiated},
+    DataType, Event, MessageType, ReadOnly, WebSocket,
+};
+
+#[test]
+fn parse_offers() {
+    let offers = Offer::parse(
+        r#"permessage-deflate ; client_max_window_bits;server_max_window_bits = "10" ,, x-foo"#,
+    )
+    .unwrap();
+    assert_eq!(
+        offers,
+        [
+            Offer::new("permessage-deflate")
+                .with_param("client_max_window_bits", None::<String>)
+                .with_param("server_max_window_bits", Some("10")),
+            Offer::new("x-foo"),
+        ]
+    );
+    assert_eq!(
+        Offer::to_header(&offers),
+        "permessage-deflate; client_max_window_bits; server_max_window_bits=10, x-foo"
+    );
+
+    for invalid in [
+        "",
+        ", ,",
+        "x-foo; bar=",
+        "x-foo; =baz",
+        r#"x-foo; bar="baz"#,
+        r#"x-foo; bar="b a z""#,
+        "x-foo bar",
+        "x-foo; bar=baz qux",
+    ] {
+        assert!(Offer::parse(invalid).is_err(), "{invalid:?}");
+    }
+}
+
+/// Toy extension that inverts every byte of the payload and marks the frame with `RSV3`.
+struct Invert;
+
+impl Extension for Invert {
+    fn name(&self) -> &str {
+        "x-invert"
+    }
+
+    fn rsv(&self) -> u8 {
+        RSV3
+    }
+
+    fn encode(&mut self, frame: &mut DataFrame) -> io::Result<()> {
+        frame.rsv |= RSV3;
+        frame.data.iter_mut().for_each(|byte| *byte = !*byte);
+        Ok(())
+    }
+
+    fn decode(&mut self, frame: &mut DataFrame) -> io::Result<()> {
+        if frame.rsv & RSV3 != 0 {
+            frame.data.iter_mut().for_each(|byte| *byte = !*byte);
+        }
+        Ok(())
+    }
+}
+
+impl Negotiator for Invert {
+    fn name(&self) -> &str {
+        "x-invert"
+    }
+
+    fn offer(&self) -> Vec<Offer> {
+        vec![Offer::new("x-invert").with_param("mode", Some("all"))]
+    }
+
+    fn accept(&self, offers: &[Offer]) -> Option<(Offer, Box<dyn Extension>)> {
+        offers
+            .iter()
+            .find(|offer| offer.param("mode") == Some(Some("all")))
+            .map(|_| (Offer::new("x-invert"), Box::new(Invert) as _))
+    }
+
+    fn confirm(&self, response: &Offer) -> Result<Box<dyn Extension>, &'static str> {
+        match response.params.is_empty() {
+            true => Ok(Box::new(Invert)),
+            false => Err("unexpected `x-invert` parameter"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn negotiate_extension() -> io::Result<()> {
+    let (client, server) = duplex(1024);
+    let server = tokio::spawn(async move {
+        let config = handshake::Config {
+            extensions: vec![Arc::new(Invert)],
+            ..Default::default()
+        };
+        handshake::accept_with(server, &config).await
+    });
+    let config = client::Config {
+        extensions: vec![Arc::new(Invert)],
+        ..Default::default()
+    };
+    let mut client = client::handshake_with(client, "example.com", "/", &config).await?;
+    let mut server = server.await?.unwrap();
+
+    assert_eq!(client.extensions().len(), 1);
+    assert_eq!(server.extensions()[0].name(), "x-invert");
+
+    client.send("Hello").await?;
+    match server.recv().await? {
+        Event::Data { ty, data, .. } => {
+            assert!(matches!(ty, DataType::Complete(MessageType::Text)));
+            assert_eq!(&*data, b"Hello");
+        }
+        ev => panic!("unexpected event: {ev:?}"),
+    }
+    Ok(())
+}
+
+fn invert() -> Negotiated {
+    Negotiated {
+        protocol: None,
+        extensions: vec![Box::new(Invert)],
+    }
+}
+
+#[tokio::test]
+async fn reserved_bits() -> io::Result<()> {
+    let mut writer = vec![];
+    let mut ws = WebSocket::server(&mut writer).with_negotiated(invert());
+    ws.send("Hi").await?;
+    // control frames are not passed through extensions
+    ws.send_ping("Hi").await?;
+    assert_eq!(writer, [0x81 | RSV3, 2, !b'H', !b'i', 0x89, 2, b'H', b'i']);
+
+    let mut ws = WebSocket::client(ReadOnly(&writer[..])).with_negotiated(invert());
+    assert!(matches!(ws.recv().await?, Event::Data { data, .. } if &*data == b"Hi"));
+    assert!(matches!(ws.recv().await?, Event::Ping(data) if &*data == b"Hi"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn unexpected_reserved_bits() -> io::Result<()> {
+    let data = [0x81 | RSV3, 2, !b'H', !b'i'];
+    let mut ws = WebSocket::client(ReadOnly(&data[..]));
+    assert!(matches!(ws.recv().await?, Event::Error(_)));
+
+    // reserved bits on control frames
+    let data = [0x89 | RSV3, 0];
+    let mut ws = WebSocket::client(ReadOnly(&data[..])).with_negotiated(invert());
+    assert!(matches!(ws.recv().await?, Event::Error(_)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn extension_not_offered() -> io::Result<()> {
+    let (client, mut server) = duplex(1024);
+    tokio::spawn(async move {
+        let mut req = [0; 1024];
+        let len = server.read(&mut req).await?;
+        let req = handshake::Request::parse(&mut &req[..len]).await?;
+        let res = handshake::response(req.sec_key()?, [("Sec-WebSocket-Extensions", "x-invert")]);
+        server.write_all(res.as_bytes()).await?;
+        Ok::<_, handshake::Error>(server)
+    });
+    let err = client::handshake(client, "example.com", "/")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, handshake::Error::BadResponse(_)));
+    Ok(())
+}
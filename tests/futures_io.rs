@@ -0,0 +1,144 @@
+#![cfg(feature = "futures-io")]
+mod common;
+
+use common::Stream;
+use futures::{executor::block_on, stream::FusedStream, task::noop_waker_ref, SinkExt, StreamExt};
+use std::{
+    io,
+    task::{Context, Poll},
+};
+use web_socket::{
+    client,
+    futures::{self as ws, WebSocket},
+    handshake::Error,
+    CloseState, Event, Frame, OutgoingMessage, Role,
+};
+
+#[test]
+fn pending_reads() -> io::Result<()> {
+    let mut server = WebSocket::server(Stream::default());
+    block_on(server.send("Hello"))?;
+    block_on(server.send_batch(["World", "!"]))?;
+
+    let mut stream = Stream::new(server.stream.output);
+    stream.trickle = true;
+    stream.pending_reads = true;
+    let mut client = WebSocket::client(stream);
+    let mut cx = Context::from_waker(noop_waker_ref());
+    for text in ["Hello", "World", "!"] {
+        // Every read is pending once, Partially received frame is kept across polls.
+        let mut polls = 0;
+        let event = loop {
+            polls += 1;
+            if let Poll::Ready(event) = client.poll_recv(&mut cx) {
+                break event?;
+            }
+        };
+        assert_eq!(event.as_text(), Some(text));
+        assert!(polls > text.len());
+    }
+    let err = block_on(client.recv()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    Ok(())
+}
+
+#[test]
+fn pending_close_reply() -> io::Result<()> {
+    let mut client = WebSocket::client(Stream::default());
+    block_on(client.send_close(()))?;
+
+    let mut stream = Stream::new(client.stream.output);
+    stream.pending_writes = true;
+    stream.write_limit = Some(1);
+    let mut server = WebSocket::server(stream);
+    let mut cx = Context::from_waker(noop_waker_ref());
+    // `Close` event is kept, Until the reply is written. And the stream isn't terminated meanwhile.
+    let event = loop {
+        if let Poll::Ready(event) = server.poll_recv(&mut cx) {
+            break event?;
+        }
+        assert!(!server.is_terminated());
+    };
+    assert!(matches!(event, Event::Close { code: 1000, .. }));
+    assert_eq!(server.stream.output, [0x88, 2, 0x03, 0xe8]);
+    assert_eq!(server.close_state(), CloseState::Closed);
+    assert!(server.is_terminated());
+    Ok(())
+}
+
+#[test]
+fn stream_and_sink() -> io::Result<()> {
+    block_on(async {
+        let mut server = WebSocket::server(Stream::default());
+        server.feed(OutgoingMessage::from("Hello")).await?;
+        server.feed(OutgoingMessage::from(&b"World"[..])).await?;
+        SinkExt::close(&mut server).await?;
+        assert_eq!(server.close_state(), CloseState::CloseSent);
+
+        let mut stream = Stream::new(server.stream.output);
+        stream.trickle = true;
+        stream.pending_reads = true;
+        let client = WebSocket::client(stream);
+        let events: Vec<_> = client.collect().await;
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].as_ref().unwrap().as_text(), Some("Hello"));
+        assert!(matches!(&events[1], Ok(Event::Data { data, .. }) if &**data == b"World"));
+        assert!(matches!(events[2], Ok(Event::Close { code: 1000, .. })));
+        Ok(())
+    })
+}
+
+#[test]
+fn accept() -> Result<(), Error> {
+    block_on(async {
+        let (req, _) = client::request("example.com", "/chat", [("Origin", "example.com")]);
+        let mut input = req.into_bytes();
+        // Frame that is sent right after the request.
+        input.extend(Frame::from("Hello").encode_with([1, 2, 3, 4]));
+
+        let mut ws = ws::accept(Stream::new(input)).await?;
+        assert_eq!(ws.role(), Role::Server);
+        assert!(ws
+            .stream
+            .output
+            .starts_with(b"HTTP/1.1 101 Switching Protocols\r\n"));
+        assert_eq!(ws.recv().await?.as_text(), Some("Hello"));
+
+        let input = b"GET /chat HTTP/1.1\r\nHost: example.com\r\n\r\n".to_vec();
+        let err = ws::accept(Stream::new(input)).await.unwrap_err();
+        assert!(matches!(err, Error::BadRequest(_)));
+        Ok(())
+    })
+}
+
+#[test]
+fn handshake() -> Result<(), Error> {
+    block_on(async {
+        let mut stream = Stream::new(vec![]);
+        stream.trickle = true;
+        stream.pending_reads = true;
+        let err = ws::handshake(stream, "example.com", "/").await.unwrap_err();
+        assert!(matches!(err, Error::Io(ref err) if err.kind() == io::ErrorKind::UnexpectedEof));
+
+        let res = b"HTTP/1.1 403 Forbidden\r\nContent-Length: 6\r\n\r\ndenied".to_vec();
+        let err = ws::handshake(Stream::new(res), "example.com", "/")
+            .await
+            .unwrap_err();
+        let Error::Rejected(res) = err else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert_eq!(res.status, 403);
+        assert_eq!(res.body, b"denied");
+        Ok(())
+    })
+}
+
+#[test]
+fn close_on_recv_error() {
+    let mut stream = Stream::default();
+    let ws = WebSocket::server(&mut stream);
+    let err = block_on(ws.close(())).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(stream.output, [0x88, 0]);
+    assert!(stream.shutdown);
+}
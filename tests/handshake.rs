@@ -0,0 +1,257 @@
+#![cfg(feature = "tokio")]
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, BufWriter};
+use web_socket::{
+    client,
+    handshake::{self, Error, Request},
+};
+
+const REQUEST: &str = "GET /chat HTTP/1.1\r\nHost: example.com\r\nUpgrade: websocket\r\nConnection: keep-alive, Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n";
+
+async fn parse(req: &str) -> Result<Request, Error> {
+    Request::parse(&mut req.as_bytes()).await
+}
+
+#[tokio::test]
+async fn parse_request() {
+    let req = parse(REQUEST).await.unwrap();
+    assert_eq!(req.method, "GET");
+    assert_eq!(req.path, "/chat");
+    assert_eq!(req.version, (1, 1));
+    assert_eq!(req.header("HOST"), Some("example.com"));
+    assert!(req.has_token("connection", "upgrade"));
+    assert_eq!(req.sec_key().unwrap(), "dGhlIHNhbXBsZSBub25jZQ==");
+
+    assert!(matches!(
+        parse("GET /chat\r\n\r\n").await,
+        Err(Error::BadRequest(_))
+    ));
+    assert!(matches!(
+        parse("GET /chat HTTP/1.1\r\nHost").await,
+        Err(Error::Io(_))
+    ));
+}
+
+#[tokio::test]
+async fn validate_request() {
+    let invalid = [
+        REQUEST.replace("GET", "POST"),
+        REQUEST.replace("HTTP/1.1", "HTTP/1.0"),
+        REQUEST.replace("Upgrade: websocket", "Upgrade: h2c"),
+        REQUEST.replace("keep-alive, Upgrade", "keep-alive"),
+        REQUEST.replace("dGhlIHNhbXBsZSBub25jZQ==", "c2hvcnQ="),
+    ];
+    for req in invalid {
+        let req = parse(&req).await.unwrap();
+        assert!(matches!(req.sec_key(), Err(Error::BadRequest(_))));
+    }
+    let req = parse(&REQUEST.replace("Version: 13", "Version: 8"))
+        .await
+        .unwrap();
+    assert!(matches!(req.sec_key(), Err(Error::UnsupportedVersion)));
+}
+
+#[tokio::test]
+async fn accept() -> std::io::Result<()> {
+    let (mut client, server) = duplex(1024);
+    client.write_all(REQUEST.as_bytes()).await?;
+    client.write_all(&[0x81, 0x80, 0, 0, 0, 0]).await?;
+
+    let mut ws = handshake::accept(server).await?;
+
+    let mut res = vec![0; 129];
+    client.read_exact(&mut res).await?;
+    assert_eq!(
+        res,
+        b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n"
+    );
+    assert!(matches!(
+        ws.recv().await?,
+        web_socket::Event::Data { data, .. } if data.is_empty()
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject() -> std::io::Result<()> {
+    let (mut client, server) = duplex(1024);
+    let req = REQUEST.replace("Version: 13", "Version: 8");
+    client.write_all(req.as_bytes()).await?;
+
+    let err = handshake::accept(server).await.unwrap_err();
+    assert!(matches!(err, Error::UnsupportedVersion));
+
+    let mut res = String::new();
+    client.read_to_string(&mut res).await?;
+    assert!(res.starts_with("HTTP/1.1 426 Upgrade Required\r\n"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn reject_malformed_request() -> std::io::Result<()> {
+    let (mut client, server) = duplex(1024);
+    client.write_all(b"GET /chat\r\n\r\n").await?;
+
+    // The rejection must be flushed, Before the stream is dropped.
+    let err = handshake::accept(BufWriter::new(server)).await.unwrap_err();
+    assert!(matches!(err, Error::BadRequest(_)));
+
+    let mut res = String::new();
+    client.read_to_string(&mut res).await?;
+    assert!(res.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_handshake() -> std::io::Result<()> {
+    let (client, server) = duplex(1024);
+    let server = tokio::spawn(handshake::accept(server));
+
+    let mut ws = client::handshake(client, "example.com", "/chat").await?;
+    ws.send("Hello").await?;
+
+    let mut ws = server.await?.unwrap();
+    assert!(matches!(ws.recv().await?, web_socket::Event::Data { data, .. } if &*data == b"Hello"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_rejected() -> std::io::Result<()> {
+    let (client, mut server) = duplex(1024);
+    server
+        .write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 6\r\n\r\ndenied")
+        .await?;
+
+    let err = client::handshake(client, "example.com", "/")
+        .await
+        .unwrap_err();
+    let Error::Rejected(res) = err else {
+        panic!("expected rejected response: {err:?}");
+    };
+    assert_eq!(res.status, 403);
+    assert_eq!(res.reason, "Forbidden");
+    assert_eq!(res.header("content-length"), Some("6"));
+    assert_eq!(res.body, b"denied");
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_invalid_accept_key() -> std::io::Result<()> {
+    let (client, mut server) = duplex(1024);
+    server
+        .write_all(b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\r\n")
+        .await?;
+
+    let err = client::handshake(client, "example.com", "/")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::BadResponse(_)));
+    assert!(matches!(
+        client::connect("wss://example.com").await,
+        Err(Error::InvalidUrl(_))
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_buffered_frames() -> std::io::Result<()> {
+    let (client, mut server) = duplex(1024);
+    let server = tokio::spawn(async move {
+        let mut req = [0; 1024];
+        let len = server.read(&mut req).await?;
+        let req = Request::parse(&mut &req[..len]).await?;
+        let res = handshake::response(req.sec_key()?, [("x-agent", "web-socket")]);
+        // response and websocket frames in a single write.
+        let mut buf = res.into_bytes();
+        buf.extend_from_slice(&[0x81, 2, b'H', b'i', 0x89, 0]);
+        server.write_all(&buf).await?;
+        std::io::Result::Ok(server)
+    });
+
+    let mut ws = client::handshake(client, "example.com", "/").await?;
+    let _server = server.await??;
+    assert_eq!(ws.buffered(), [0x81, 2, b'H', b'i', 0x89, 0]);
+    assert!(matches!(ws.recv().await?, web_socket::Event::Data { data, .. } if &*data == b"Hi"));
+    assert_eq!(ws.buffered(), [0x89, 0]);
+
+    let (_stream, buffered) = ws.into_parts();
+    assert_eq!(buffered, [0x89, 0]);
+    Ok(())
+}
+
+async fn negotiate(offered: &[&str], config: handshake::Config) -> Result<Option<String>, Error> {
+    let (client, server) = duplex(1024);
+    let server = tokio::spawn(async move {
+        let ws = handshake::accept_with(server, &config).await?;
+        Ok::<_, Error>(ws.protocol().map(String::from))
+    });
+    let config = client::Config {
+        protocols: offered.iter().map(|p| p.to_string()).collect(),
+        ..Default::default()
+    };
+
+    let ws = client::handshake_with(client, "example.com", "/", &config).await?;
+    let selected = server.await.unwrap()?;
+    assert_eq!(ws.protocol(), selected.as_deref());
+    Ok(selected)
+}
+
+#[tokio::test]
+async fn subprotocol() -> Result<(), Error> {
+    let config = handshake::Config {
+        protocols: vec!["v3".into(), "v2".into(), "v1".into()],
+        ..Default::default()
+    };
+    assert_eq!(
+        negotiate(&["v1", "v2"], config).await?.as_deref(),
+        Some("v2")
+    );
+
+    let config = handshake::Config {
+        protocols: vec!["v3".into()],
+        ..Default::default()
+    };
+    assert_eq!(negotiate(&["v1", "v2"], config).await?, None);
+
+    let config = handshake::Config {
+        protocols: vec!["v2".into()],
+        select_protocol: Some(Box::new(|offered| offered.last().map(|p| p.to_string()))),
+        ..Default::default()
+    };
+    assert_eq!(
+        negotiate(&["v1", "v3"], config).await?.as_deref(),
+        Some("v3")
+    );
+
+    let config = handshake::Config {
+        select_protocol: Some(Box::new(|_| Some("v4".into()))),
+        ..Default::default()
+    };
+    assert_eq!(negotiate(&["v1"], config).await?, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn subprotocol_not_offered() -> std::io::Result<()> {
+    let (client, mut server) = duplex(1024);
+    let server = tokio::spawn(async move {
+        let mut req = [0; 1024];
+        let len = server.read(&mut req).await?;
+        let req = Request::parse(&mut &req[..len]).await?;
+        assert_eq!(req.protocols(), ["chat"]);
+
+        let res = handshake::response(req.sec_key()?, [("Sec-WebSocket-Protocol", "superchat")]);
+        server.write_all(res.as_bytes()).await?;
+        std::io::Result::Ok(server)
+    });
+    let config = client::Config {
+        protocols: vec!["chat".into()],
+        ..Default::default()
+    };
+
+    let err = client::handshake_with(client, "example.com", "/", &config)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::BadResponse(_)));
+    server.await??;
+    Ok(())
+}
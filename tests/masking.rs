@@ -0,0 +1,94 @@
+#![cfg(feature = "tokio")]
+use std::{
+    io::{self, IoSlice},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::AsyncWrite;
+use web_socket::{Event, Frame, ReadOnly, WebSocket};
+
+fn mask_bytewise(data: &[u8], mask: [u8; 4]) -> Vec<u8> {
+    data.iter()
+        .enumerate()
+        .map(|(i, byte)| byte ^ mask[i % 4])
+        .collect()
+}
+
+#[tokio::test]
+async fn masking() -> io::Result<()> {
+    let mask = [0x37, 0xfa, 0x21, 0x3d];
+    let data: Vec<u8> = (0..=255).cycle().take(70000).collect();
+
+    // Covers every remainder of the 32, 16 and 8 bytes chunks, And all extended payload lengths.
+    let lens = (0..=130).chain([65535, 65536, 70000]);
+    for len in lens {
+        let data = &data[..len];
+        let frame = Frame::from(data).encode_with(mask);
+        let head_len = frame.len() - len;
+        assert_eq!(frame[head_len - 4..head_len], mask);
+        assert_eq!(frame[head_len..], mask_bytewise(data, mask), "len: {len}");
+
+        let mut ws = WebSocket::server(ReadOnly(&frame[..]));
+        assert!(matches!(ws.recv().await?, Event::Data { data: d, .. } if *d == *data));
+    }
+    Ok(())
+}
+
+/// Accepts at most 3 bytes per write, With vectored I/O support.
+struct Vectored(Vec<u8>);
+
+impl AsyncWrite for Vectored {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let amt = buf.len().min(3);
+        self.0.extend_from_slice(&buf[..amt]);
+        Poll::Ready(Ok(amt))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let buf = bufs
+            .iter()
+            .find(|buf| !buf.is_empty())
+            .map_or(&[][..], |buf| buf);
+        self.poll_write(cx, buf)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn client_send() -> io::Result<()> {
+    let data: Vec<u8> = (0..=255).cycle().take(70000).collect();
+    let lens = [0, 5, 125, 126, 1000, 65536, 70000];
+
+    let mut ws = WebSocket::client(vec![]);
+    let mut vectored = WebSocket::client(Vectored(vec![]));
+    for len in lens {
+        ws.send(&data[..len]).await?;
+        vectored.send(&data[..len]).await?;
+    }
+    for frames in [ws.stream, vectored.stream.0] {
+        let mut ws = WebSocket::server(ReadOnly(&frames[..]));
+        for len in lens {
+            assert!(matches!(ws.recv().await?, Event::Data { data: d, .. } if *d == data[..len]));
+        }
+    }
+    Ok(())
+}
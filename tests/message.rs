@@ -0,0 +1,99 @@
+#![cfg(feature = "tokio")]
+use std::io;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+use web_socket::{DataType, Event, MessageType, ProtocolError, ReadOnly, WebSocket};
+
+mod common;
+use common::frame;
+
+#[tokio::test]
+async fn reassemble_fragments() -> io::Result<()> {
+    let frames = [
+        frame(1, true, b"Hello", None),
+        frame(1, false, b"Hello", None),
+        frame(0, false, b", ", None),
+        frame(9, true, b"ping", None),
+        frame(0, true, b"World!", None),
+        frame(2, false, b"bin", None),
+        frame(0, true, b"ary", None),
+    ]
+    .concat();
+    let mut ws = WebSocket::client(ReadOnly(&frames[..]));
+
+    let mut events = vec![];
+    for _ in 0..4 {
+        events.push(ws.recv_message().await?);
+    }
+    assert!(matches!(
+        &events[0],
+        Event::Data { ty: DataType::Complete(MessageType::Text), data, .. } if &**data == b"Hello"
+    ));
+    // Control frames in between fragments are returned as they arrive.
+    assert!(matches!(&events[1], Event::Ping(data) if &**data == b"ping"));
+    assert!(matches!(
+        &events[2],
+        Event::Data { ty: DataType::Complete(MessageType::Text), data, .. } if &**data == b"Hello, World!"
+    ));
+    assert!(matches!(
+        &events[3],
+        Event::Data { ty: DataType::Complete(MessageType::Binary), data, .. } if &**data == b"binary"
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn max_message_len() -> io::Result<()> {
+    let chunk = "x".repeat(100);
+    let frames = [
+        frame(2, false, chunk.as_bytes(), None),
+        frame(0, false, chunk.as_bytes(), None),
+        frame(0, true, chunk.as_bytes(), None),
+    ]
+    .concat();
+
+    let mut ws = WebSocket::client(ReadOnly(&frames[..]));
+    ws.max_payload_len = 100;
+    ws.max_message_len = 300;
+    assert!(matches!(ws.recv_message().await?, Event::Data { data, .. } if data.len() == 300));
+
+    let mut ws = WebSocket::client(ReadOnly(&frames[..]));
+    ws.max_payload_len = 100;
+    ws.max_message_len = 250;
+    assert!(matches!(
+        ws.recv_message().await?,
+        Event::Error(ProtocolError::MessageTooLarge)
+    ));
+
+    // Limit also applies to fragments, That are received by `recv`.
+    let mut ws = WebSocket::client(ReadOnly(&frames[..]));
+    ws.max_message_len = 250;
+    assert!(matches!(ws.recv().await?, Event::Data { .. }));
+    assert!(matches!(ws.recv().await?, Event::Data { .. }));
+    assert!(matches!(
+        ws.recv().await?,
+        Event::Error(ProtocolError::MessageTooLarge)
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn message_too_large_close_code() -> io::Result<()> {
+    let (client, mut server) = duplex(1024);
+    let mut ws = WebSocket::client(client);
+    ws.max_message_len = 8;
+    server.write_all(&frame(1, false, b"Hello", None)).await?;
+    server.write_all(&frame(0, true, b"World", None)).await?;
+
+    assert!(matches!(
+        ws.recv_message().await?,
+        Event::Error(ProtocolError::MessageTooLarge)
+    ));
+    assert!(ws.flush().await.is_ok());
+
+    let mut buf = [0; 8];
+    server.read_exact(&mut buf).await?;
+    assert_eq!(buf[..2], [0x88, 0x82]);
+    let code = [buf[2] ^ buf[6], buf[3] ^ buf[7]];
+    assert_eq!(u16::from_be_bytes(code), 1009);
+    Ok(())
+}
@@ -0,0 +1,147 @@
+#![cfg(feature = "tokio")]
+use std::io;
+use web_socket::{Event, PreparedFrame, ReadOnly, WebSocket};
+
+#[tokio::test]
+async fn server_sockets() -> io::Result<()> {
+    let frame = PreparedFrame::new("Hello, World!");
+    for _ in 0..3 {
+        let mut ws = WebSocket::server(vec![]);
+        ws.send_prepared(&frame).await?;
+        ws.send_prepared(&frame.clone()).await?;
+        assert_eq!(ws.stream, frame.as_bytes().repeat(2));
+
+        let mut client = WebSocket::client(ReadOnly(&ws.stream[..]));
+        for _ in 0..2 {
+            assert!(
+                matches!(client.recv().await?, Event::Data { data, .. } if &*data == b"Hello, World!")
+            );
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_sockets() -> io::Result<()> {
+    let frame = PreparedFrame::new(&b"binary"[..]);
+    let mut ws = WebSocket::client(vec![]);
+    ws.send_prepared(&frame).await?;
+    // Frame is re-masked.
+    assert_eq!(ws.stream[1], 0x80 | 6);
+
+    let mut server = WebSocket::server(ReadOnly(&ws.stream[..]));
+    assert!(matches!(server.recv().await?, Event::Data { data, .. } if &*data == b"binary"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn queued_and_control_frames() -> io::Result<()> {
+    let ping = PreparedFrame::new(web_socket::Frame {
+        fin: true,
+        opcode: 9,
+        data: b"ping",
+    });
+    let mut ws = WebSocket::server(vec![]);
+    ws.write_buffer_size = 1024;
+    ws.send_prepared(&PreparedFrame::new("Hello")).await?;
+    ws.send_prepared(&ping).await?;
+    ws.flush().await?;
+
+    let mut client = WebSocket::client(ReadOnly(&ws.stream[..]));
+    assert!(matches!(client.recv().await?, Event::Ping(data) if &*data == b"ping"));
+    assert!(matches!(client.recv().await?, Event::Data { data, .. } if &*data == b"Hello"));
+    Ok(())
+}
+
+#[cfg(feature = "deflate")]
+mod deflate {
+    use super::*;
+    use web_socket::{
+        deflate::PerMessageDeflate,
+        extension::{Negotiator, Offer, RSV1},
+        handshake::Negotiated,
+    };
+
+    const JSON: &str =
+        r#"{"event":"ticker","symbol":"BTC-USD","price":"27000.00","volume":"1200.50"}"#;
+
+    /// Returns a server websocket, And the client extensions negotiated with `offer`.
+    fn pair(offer: Offer) -> (WebSocket<Vec<u8>>, Negotiated) {
+        let config = PerMessageDeflate::default();
+        let (response, ext) = config.accept(&[offer]).unwrap();
+        let server = WebSocket::server(vec![]).with_negotiated(Negotiated {
+            protocol: None,
+            extensions: vec![ext],
+        });
+        let client = Negotiated {
+            protocol: None,
+            extensions: vec![config.confirm(&response).unwrap()],
+        };
+        (server, client)
+    }
+
+    async fn recv_all(frames: &[u8], negotiated: Negotiated) -> io::Result<Vec<Box<[u8]>>> {
+        let mut ws = WebSocket::client(ReadOnly(frames)).with_negotiated(negotiated);
+        let mut messages = vec![];
+        while !ws.buffered().is_empty() || !ws.stream.0.is_empty() {
+            match ws.recv().await? {
+                Event::Data { data, .. } => messages.push(data),
+                ev => panic!("unexpected event: {ev:?}"),
+            }
+        }
+        Ok(messages)
+    }
+
+    #[tokio::test]
+    async fn compressed() -> io::Result<()> {
+        let message = JSON.repeat(4);
+        let frame = PreparedFrame::compressed(message.as_str(), &PerMessageDeflate::default())?;
+        let compressed = frame.compressed_bytes().unwrap();
+        assert!(compressed.len() < frame.as_bytes().len());
+
+        let (mut server, client) = pair(Offer::new("permessage-deflate"));
+        server.send(message.as_str()).await?;
+        server.send_prepared(&frame).await?;
+        // Compression context is reset, So the next message doesn't refer to the prepared one.
+        server.send(message.as_str()).await?;
+        assert!(server
+            .stream
+            .windows(compressed.len())
+            .any(|w| w == compressed));
+
+        let messages = recv_all(&server.stream, client).await?;
+        assert_eq!(messages.len(), 3);
+        for data in messages {
+            assert_eq!(*data, *message.as_bytes());
+        }
+
+        // Without extensions, The uncompressed frame is sent.
+        let mut ws = WebSocket::server(vec![]);
+        ws.send_prepared(&frame).await?;
+        assert_eq!(ws.stream, frame.as_bytes());
+        assert_eq!(ws.stream[0] & RSV1, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn smaller_window() -> io::Result<()> {
+        // Repeated at a distance of 1500 bytes, Which is beyond a window of 1 KB.
+        let block: String = (0..1500u32)
+            .map(|i| char::from(b'a' + (i.wrapping_mul(2654435761) >> 24) as u8 % 26))
+            .collect();
+        let message = block.repeat(2);
+        let frame = PreparedFrame::compressed(message.as_str(), &PerMessageDeflate::default())?;
+
+        // Client limits the server window, So the message is compressed by the websocket itself.
+        let offer =
+            Offer::new("permessage-deflate").with_param("server_max_window_bits", Some("10"));
+        let (mut server, client) = pair(offer);
+        server.send_prepared(&frame).await?;
+        assert_eq!(server.stream[0] & RSV1, RSV1);
+        assert!(server.stream.len() > frame.compressed_bytes().unwrap().len());
+
+        let messages = recv_all(&server.stream, client).await?;
+        assert_eq!(*messages[0], *message.as_bytes());
+        Ok(())
+    }
+}
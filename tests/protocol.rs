@@ -0,0 +1,150 @@
+#![cfg(feature = "tokio")]
+use std::io;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+use web_socket::{CloseCode, CloseState, Event, ProtocolError, ReadOnly, WebSocket};
+
+async fn recv_error(frames: &[u8]) -> io::Result<ProtocolError> {
+    let mut ws = WebSocket::client(ReadOnly(frames));
+    ws.max_payload_len = 1024;
+    loop {
+        match ws.recv().await? {
+            Event::Error(err) => return Ok(err),
+            Event::Data { .. } => {}
+            ev => panic!("unexpected event: {ev:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn protocol_errors() -> io::Result<()> {
+    use ProtocolError::*;
+    let cases: [(&[u8], ProtocolError); 12] = [
+        (&[0xc1, 0], ReservedBits),
+        (&[0x81, 0x80, 0, 0, 0, 0], MaskedFrame),
+        (&[0x09, 0], FragmentedControlFrame),
+        (&[0x89, 126, 0, 126], ControlFrameTooLarge),
+        (&[0x8b, 0], UnknownOpcode),
+        (&[0x83, 0], UnknownOpcode),
+        (&[0x01, 0, 0x87, 0], UnknownOpcode),
+        (&[0x80, 0], InvalidContinuation),
+        (&[0x01, 0, 0x81, 0], InvalidContinuation),
+        (&[0x82, 126, 4, 1], PayloadTooLarge),
+        (&[0x88, 2, 0x03, 0xed], InvalidCloseCode),
+        (&[0x88, 3, 0x03, 0xe8, 0xff], InvalidUtf8),
+    ];
+    for (frames, expected) in cases {
+        assert_eq!(recv_error(frames).await?, expected, "{frames:?}");
+    }
+    assert_eq!(recv_error(&[0x88, 1, 0x03]).await?, InvalidCloseCode);
+
+    let mut ws = WebSocket::server(ReadOnly(&[0x81, 0][..]));
+    assert!(matches!(ws.recv().await?, Event::Error(UnmaskedFrame)));
+    Ok(())
+}
+
+#[test]
+fn close_codes() {
+    assert_eq!(
+        ProtocolError::ReservedBits.close_code(),
+        CloseCode::ProtocolError
+    );
+    assert_eq!(
+        ProtocolError::PayloadTooLarge.close_code(),
+        CloseCode::MessageTooBig
+    );
+    assert_eq!(
+        ProtocolError::MessageTooLarge.close_code(),
+        CloseCode::MessageTooBig
+    );
+    assert_eq!(
+        ProtocolError::InvalidUtf8.close_code(),
+        CloseCode::InvalidPayload
+    );
+    let err: &dyn std::error::Error = &ProtocolError::UnknownOpcode;
+    assert_eq!(err.to_string(), "unknown opcode");
+}
+
+#[tokio::test]
+async fn close_on_error() -> io::Result<()> {
+    let (client, mut server) = duplex(1024);
+    let mut ws = WebSocket::client(client);
+    server.write_all(&[0x82, 126, 4, 1]).await?;
+    ws.max_payload_len = 1024;
+
+    assert!(matches!(
+        ws.recv().await?,
+        Event::Error(ProtocolError::PayloadTooLarge)
+    ));
+    // `Close` frame with status code `1009` is sent, Before the event is returned.
+    assert_eq!(ws.close_state(), CloseState::Closed);
+    assert!(ws.send("Hello").await.is_err());
+
+    let mut buf = [0; 8];
+    server.read_exact(&mut buf).await?;
+    assert_eq!(buf[..2], [0x88, 0x82]);
+    let code = [buf[2] ^ buf[6], buf[3] ^ buf[7]];
+    assert_eq!(u16::from_be_bytes(code), 1009);
+    Ok(())
+}
+
+#[tokio::test]
+async fn error_without_close_frame() -> io::Result<()> {
+    let (client, mut server) = duplex(1024);
+    let mut ws = WebSocket::client(client);
+    ws.close_on_error = false;
+    server.write_all(&[0xc1, 0]).await?;
+
+    assert!(matches!(
+        ws.recv().await?,
+        Event::Error(ProtocolError::ReservedBits)
+    ));
+    assert_eq!(ws.close_state(), CloseState::Closed);
+    ws.close(CloseCode::ProtocolError).await?;
+
+    let mut buf = vec![];
+    server.read_to_end(&mut buf).await?;
+    assert!(buf.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn payload_lengths() -> io::Result<()> {
+    use ProtocolError::*;
+    let high_bit = [0x82, 127, 0x80, 0, 0, 0, 0, 0, 0, 1];
+    assert_eq!(recv_error(&high_bit).await?, InvalidLength);
+    assert_eq!(
+        recv_error(&[0x82, 127, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).await?,
+        InvalidLength
+    );
+    #[cfg(target_pointer_width = "32")]
+    assert_eq!(
+        recv_error(&[0x82, 127, 0, 0, 0, 1, 0, 0, 0, 0]).await?,
+        LengthOverflow
+    );
+    assert_eq!(LengthOverflow.close_code(), CloseCode::MessageTooBig);
+
+    // Non-minimal encodings are accepted by default.
+    let frames: [&[u8]; 2] = [
+        &[0x82, 126, 0, 1, 0xff],
+        &[0x82, 127, 0, 0, 0, 0, 0, 0, 0, 1, 0xff],
+    ];
+    for frame in frames {
+        let mut ws = WebSocket::client(ReadOnly(frame));
+        assert!(matches!(ws.recv().await?, Event::Data { data, .. } if *data == [0xff]));
+
+        let mut ws = WebSocket::client(ReadOnly(frame));
+        ws.strict_length_encoding = true;
+        assert!(matches!(ws.recv().await?, Event::Error(NonMinimalLength)));
+    }
+
+    // Minimal encodings are accepted in strict mode.
+    let mut frame = vec![0x82, 126, 0, 126];
+    frame.extend_from_slice(&[0; 126]);
+    frame.extend_from_slice(&[0x82, 127, 0, 0, 0, 0, 0, 1, 0, 0]);
+    frame.extend_from_slice(&[0; 0x10000]);
+    let mut ws = WebSocket::client(ReadOnly(&frame[..]));
+    ws.strict_length_encoding = true;
+    assert!(matches!(ws.recv().await?, Event::Data { data, .. } if data.len() == 126));
+    assert!(matches!(ws.recv().await?, Event::Data { data, .. } if data.len() == 0x10000));
+    Ok(())
+}
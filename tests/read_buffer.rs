@@ -0,0 +1,100 @@
+#![cfg(feature = "tokio")]
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, ReadBuf};
+use web_socket::{Event, ReadOnly, WebSocket};
+
+mod common;
+
+/// Counts the number of reads from the underlying stream.
+struct Counter {
+    data: Vec<u8>,
+    pos: usize,
+    reads: usize,
+}
+
+impl AsyncRead for Counter {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.reads += 1;
+        let amt = buf.remaining().min(self.data.len() - self.pos);
+        buf.put_slice(&self.data[self.pos..self.pos + amt]);
+        self.pos += amt;
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn frames(count: usize, data: &[u8]) -> Vec<u8> {
+    (0..count)
+        .flat_map(|_| common::frame(2, true, data, Some([1, 2, 3, 4])))
+        .collect()
+}
+
+async fn recv_all(
+    ws: &mut WebSocket<ReadOnly<Counter>>,
+    count: usize,
+    len: usize,
+) -> io::Result<()> {
+    for _ in 0..count {
+        assert!(matches!(ws.recv().await?, Event::Data { data, .. } if data.len() == len));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn small_frames() -> io::Result<()> {
+    let data = frames(100, b"Hello");
+    let mut ws = WebSocket::server(ReadOnly(Counter {
+        data: data.clone(),
+        pos: 0,
+        reads: 0,
+    }));
+    recv_all(&mut ws, 100, 5).await?;
+    // 100 frames of 11 bytes, Fit in a single read of the 8 KB buffer.
+    assert!(ws.stream.0.reads <= 2, "reads: {}", ws.stream.0.reads);
+
+    let mut ws = WebSocket::server(ReadOnly(Counter {
+        data,
+        pos: 0,
+        reads: 0,
+    }));
+    ws.read_buffer_size = 0;
+    recv_all(&mut ws, 100, 5).await?;
+    assert!(ws.stream.0.reads >= 300, "reads: {}", ws.stream.0.reads);
+    Ok(())
+}
+
+#[tokio::test]
+async fn large_frames() -> io::Result<()> {
+    let data = frames(3, &[0xab; 100000]);
+    let mut ws = WebSocket::server(ReadOnly(Counter {
+        data,
+        pos: 0,
+        reads: 0,
+    }));
+    ws.read_buffer_size = 1024;
+    recv_all(&mut ws, 3, 100000).await?;
+    assert!(ws.stream.0.reads < 100, "reads: {}", ws.stream.0.reads);
+    Ok(())
+}
+
+#[tokio::test]
+async fn buffered_bytes_into_parts() -> io::Result<()> {
+    let data = frames(3, b"Hello");
+    let mut ws = WebSocket::server(ReadOnly(Counter {
+        data: data.clone(),
+        pos: 0,
+        reads: 0,
+    }));
+    assert!(matches!(ws.recv().await?, Event::Data { .. }));
+    // Remaining frames are buffered, They are returned along with the stream.
+    let (_, buffered) = ws.into_parts();
+    assert_eq!(buffered, data[data.len() / 3..]);
+    Ok(())
+}
@@ -0,0 +1,132 @@
+#![cfg(feature = "tokio")]
+use std::{io, time::Duration};
+use tokio::{
+    io::duplex,
+    net::{TcpListener, TcpStream},
+};
+use web_socket::{client, handshake, CloseCode, CloseState, Event, ReuniteError, WebSocket};
+
+#[tokio::test]
+async fn concurrent_halves() -> io::Result<()> {
+    let (client, server) = duplex(1024);
+    let mut client = WebSocket::client(client);
+    let (mut reader, mut writer) = WebSocket::server(server).split();
+
+    let pinger = tokio::spawn(async move {
+        for _ in 0..3 {
+            writer.send_ping("ping").await?;
+        }
+        io::Result::Ok(writer)
+    });
+    for _ in 0..3 {
+        assert!(matches!(client.recv().await?, Event::Ping(data) if &*data == b"ping"));
+        client.send("Hello").await?;
+    }
+    for _ in 0..3 {
+        assert!(matches!(reader.recv().await?, Event::Data { data, .. } if &*data == b"Hello"));
+    }
+    let writer = pinger.await??;
+
+    let mut server = reader.reunite(writer).unwrap();
+    server.send("Bye").await?;
+    assert!(matches!(client.recv().await?, Event::Data { data, .. } if &*data == b"Bye"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn close_is_shared() -> io::Result<()> {
+    let (client, server) = duplex(1024);
+    let (mut reader, writer) = WebSocket::server(server).split();
+    assert!(!writer.is_closed());
+
+    WebSocket::client(client)
+        .send_close(CloseCode::Normal)
+        .await?;
+    assert!(matches!(
+        reader.recv().await?,
+        Event::Close { code: 1000, .. }
+    ));
+    assert!(reader.is_closed());
+    assert!(writer.is_closed());
+    assert_eq!(writer.close_state(), CloseState::CloseReceived);
+    Ok(())
+}
+
+#[tokio::test]
+async fn writer_replies_to_close() -> io::Result<()> {
+    let (client, server) = duplex(1024);
+    let mut client = WebSocket::client(client);
+    let (mut reader, mut writer) = WebSocket::server(server).split();
+
+    // Same as the example of `WebSocket::split`.
+    let heartbeat = tokio::spawn(async move {
+        while writer.send_ping("heartbeat").await.is_ok() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        writer
+    });
+    client.send_close(CloseCode::Normal).await?;
+    assert!(matches!(
+        reader.recv().await?,
+        Event::Close { code: 1000, .. }
+    ));
+
+    // The reply is written by the write half, Before its `send_ping` fails.
+    loop {
+        match client.recv().await? {
+            Event::Ping(_) => {}
+            Event::Close { code, .. } => break assert_eq!(code, 1000),
+            event => panic!("unexpected event: {event:?}"),
+        }
+    }
+    assert_eq!(client.close_state(), CloseState::Closed);
+    let writer = heartbeat.await.unwrap();
+    assert_eq!(writer.close_state(), CloseState::Closed);
+
+    // A flush also writes the reply.
+    let (client, server) = duplex(1024);
+    let mut client = WebSocket::client(client);
+    let (mut reader, mut writer) = WebSocket::server(server).split();
+    client.send_close(CloseCode::Away).await?;
+    assert!(matches!(reader.recv().await?, Event::Close { .. }));
+    writer.flush().await?;
+    assert!(matches!(
+        client.recv().await?,
+        Event::Close { code: 1001, .. }
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn reunite_mismatched_halves() {
+    let (a, b) = duplex(1024);
+    let (reader_a, writer_a) = WebSocket::client(a).split();
+    let (reader_b, writer_b) = WebSocket::server(b).split();
+
+    let err = reader_a.reunite(writer_b).unwrap_err();
+    let ReuniteError(reader_a, writer_b) = *err;
+    assert!(reader_a.reunite(writer_a).is_ok());
+    assert!(reader_b.reunite(writer_b).is_ok());
+}
+
+#[tokio::test]
+async fn split_owned_halves() -> io::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await?;
+        let ws = handshake::accept(stream).await?;
+        let (mut reader, mut writer) = ws.split_with(TcpStream::into_split);
+        while let Event::Data { data, .. } = reader.recv().await? {
+            writer.send(&*data).await?;
+        }
+        // The reply to the `Close` frame is written by the write half.
+        writer.flush().await
+    });
+
+    let mut ws = client::connect(&format!("ws://{addr}")).await?;
+    ws.send("Echo").await?;
+    assert!(matches!(ws.recv().await?, Event::Data { data, .. } if &*data == b"Echo"));
+    ws.close(()).await?;
+    server.await?
+}
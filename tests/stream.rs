@@ -0,0 +1,133 @@
+#![cfg(feature = "tokio")]
+use futures::{stream, SinkExt, StreamExt};
+use std::io;
+use tokio::io::duplex;
+use web_socket::{CloseState, Event, OutgoingMessage, ReadOnly, WebSocket};
+
+#[tokio::test]
+async fn forward() -> io::Result<()> {
+    let (client, server) = duplex(1024);
+    let mut client = WebSocket::client(client);
+    let server = WebSocket::server(server);
+
+    let (reader, writer) = server.split();
+    let echo = tokio::spawn(
+        reader
+            .filter_map(|event| async {
+                match event {
+                    Ok(Event::Data { data, .. }) => Some(Ok(OutgoingMessage::Binary(data.into()))),
+                    Ok(_) => None,
+                    Err(err) => Some(Err(err)),
+                }
+            })
+            .forward(writer),
+    );
+
+    let mut messages = stream::iter(["Hello", "World"].map(|text| Ok(OutgoingMessage::from(text))));
+    client.send_all(&mut messages).await?;
+    for text in ["Hello", "World"] {
+        let event = client.next().await.unwrap()?;
+        assert!(matches!(event, Event::Data { data, .. } if &*data == text.as_bytes()));
+    }
+
+    // Closing the client sink, Ends the stream of the server. Then the server replies to the `Close` frame.
+    SinkExt::close(&mut client).await?;
+    assert_eq!(client.close_state(), CloseState::CloseSent);
+    echo.await.unwrap()?;
+
+    assert!(matches!(
+        client.next().await.unwrap()?,
+        Event::Close { code: 1000, .. }
+    ));
+    assert_eq!(client.close_state(), CloseState::Closed);
+    assert!(client.next().await.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn backpressure() -> io::Result<()> {
+    let mut ws = WebSocket::server(Vec::new());
+    // Every message is written, Before the next message is accepted.
+    ws.feed(OutgoingMessage::from("Hello")).await?;
+    assert!(ws.stream.is_empty());
+    ws.feed(OutgoingMessage::from("World")).await?;
+    assert_eq!(ws.stream.len(), 7);
+    ws.flush().await?;
+    assert_eq!(ws.stream.len(), 14);
+
+    // Messages are queued, Until they reach `write_buffer_size`.
+    let mut ws = WebSocket::server(Vec::new());
+    ws.write_buffer_size = 100;
+    for _ in 0..10 {
+        ws.feed(OutgoingMessage::from("Hello")).await?;
+    }
+    assert!(ws.stream.is_empty());
+    SinkExt::flush(&mut ws).await?;
+    assert_eq!(ws.stream.len(), 70);
+    Ok(())
+}
+
+#[tokio::test]
+async fn control_messages_jump_queue() -> io::Result<()> {
+    let mut ws = WebSocket::server(Vec::new());
+    ws.write_buffer_size = 100;
+    ws.feed(OutgoingMessage::from("Hello")).await?;
+    ws.feed(OutgoingMessage::Ping(b"heartbeat".to_vec()))
+        .await?;
+    ws.feed(OutgoingMessage::from("World")).await?;
+    ws.feed(OutgoingMessage::Pong(vec![])).await?;
+    assert!(ws.stream.is_empty());
+    SinkExt::flush(&mut ws).await?;
+
+    let mut ws = WebSocket::client(ReadOnly(&ws.stream[..]));
+    assert!(matches!(ws.recv().await?, Event::Pong(data) if data.is_empty()));
+    assert!(matches!(ws.recv().await?, Event::Ping(data) if &*data == b"heartbeat"));
+    assert_eq!(ws.recv().await?.as_text(), Some("Hello"));
+    assert_eq!(ws.recv().await?.as_text(), Some("World"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn stream_ends() -> io::Result<()> {
+    let mut ws = WebSocket::server(Vec::new());
+    ws.send("Hello").await?;
+    ws.send_close(()).await?;
+
+    let mut ws = WebSocket::client(ReadOnly(&ws.stream[..]));
+    assert_eq!(ws.next().await.unwrap()?.as_text(), Some("Hello"));
+    assert!(matches!(
+        ws.next().await.unwrap()?,
+        Event::Close { code: 1000, .. }
+    ));
+    assert!(ws.next().await.is_none());
+
+    // Stream also ends after an error of the underlying stream.
+    let mut ws = WebSocket::client(ReadOnly(&b"\x81"[..]));
+    let err = ws.next().await.unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert!(ws.next().await.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn send_after_close() -> io::Result<()> {
+    let mut ws = WebSocket::client(Vec::new());
+    let close = OutgoingMessage::Close {
+        code: 4000,
+        reason: "Bye".into(),
+    };
+    SinkExt::send(&mut ws, close).await?;
+    assert_eq!(ws.close_state(), CloseState::CloseSent);
+
+    let mut server = WebSocket::server(ReadOnly(&ws.stream[..]));
+    assert!(matches!(
+        server.recv().await?,
+        Event::Close { code: 4000, reason } if &*reason == "Bye"
+    ));
+
+    let err = SinkExt::send(&mut ws, OutgoingMessage::from("Hello"))
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+    Ok(())
+}
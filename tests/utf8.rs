@@ -0,0 +1,111 @@
+#![cfg(feature = "tokio")]
+use std::io;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+use web_socket::{Event, MessageType, ProtocolError, ReadOnly, WebSocket};
+
+mod common;
+
+/// Split a text message into fragments, At byte offsets `at`.
+fn fragments(msg: &[u8], at: &[usize]) -> Vec<u8> {
+    let ends = at.iter().copied().chain([msg.len()]);
+    let starts = [0].into_iter().chain(at.iter().copied());
+    let mut frames = vec![];
+    for (i, (start, end)) in starts.zip(ends).enumerate() {
+        let opcode = if i == 0 { 1 } else { 0 };
+        frames.extend(common::frame(
+            opcode,
+            end == msg.len(),
+            &msg[start..end],
+            None,
+        ));
+    }
+    frames
+}
+
+#[tokio::test]
+async fn split_code_points() -> io::Result<()> {
+    let msg = "héllo, wörld €𝄞";
+    // split inside of `é`, `€` and `𝄞` (across three fragments)
+    let at = [2, 17, 19, 20, 21];
+    let frames = fragments(msg.as_bytes(), &at);
+    let mut ws = WebSocket::client(ReadOnly(&frames[..]));
+    let mut text = vec![];
+    for (start, end) in [0]
+        .iter()
+        .chain(&at)
+        .zip(at.iter().chain([msg.len()].iter()))
+    {
+        let Event::Data { ty, data, .. } = ws.recv().await? else {
+            panic!("expected a text fragment");
+        };
+        assert_eq!(ty.ty(), MessageType::Text);
+        // fragments are passed through as they are received.
+        assert_eq!(&*data, &msg.as_bytes()[*start..*end]);
+        text.extend_from_slice(&data);
+    }
+    assert_eq!(String::from_utf8(text).unwrap(), msg);
+
+    // a fragment that splits a code point is not returned as text.
+    let mut ws = WebSocket::client(ReadOnly(&frames[..]));
+    assert_eq!(ws.recv().await?.as_text(), None);
+
+    let mut ws = WebSocket::client(ReadOnly(&frames[..]));
+    let event = ws.recv_message().await?;
+    assert!(matches!(&event, Event::Data { data, .. } if **data == *msg.as_bytes()));
+    assert_eq!(event.as_text(), Some(msg));
+    assert_eq!(event.into_text().as_deref(), Some(msg));
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_utf8() -> io::Result<()> {
+    let cases: [&[u8]; 6] = [
+        // fails on the first fragment, before the message is complete.
+        &[0x01, 2, 0xff, b'a'],
+        &[0x81, 3, 0xed, 0xa0, 0x80],
+        // incomplete code point at the end of the message
+        &[0x01, 2, b'a', 0xe2, 0x80, 1, 0x82],
+        &[0x01, 1, 0xe2, 0x00, 1, 0x41],
+        // invalid code point, That is split across fragments.
+        &[0x01, 1, 0xed, 0x00, 1, 0xa0, 0x80, 1, 0x80],
+        &[0x01, 1, 0xf0, 0x80, 3, 0x80, 0x80, 0x80],
+    ];
+    for frames in cases {
+        let mut ws = WebSocket::client(ReadOnly(frames));
+        let err = loop {
+            match ws.recv().await? {
+                Event::Error(err) => break err,
+                Event::Data { .. } => {}
+                ev => panic!("unexpected event: {ev:?}"),
+            }
+        };
+        assert_eq!(err, ProtocolError::InvalidUtf8, "{frames:?}");
+    }
+
+    // binary messages are not validated.
+    let mut ws = WebSocket::client(ReadOnly(&[0x82, 1, 0xff][..]));
+    let event = ws.recv().await?;
+    assert!(matches!(&event, Event::Data { data, .. } if **data == [0xff]));
+    assert_eq!(event.as_text(), None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_payload_close_code() -> io::Result<()> {
+    let (client, mut server) = duplex(1024);
+    let mut ws = WebSocket::client(client);
+    server.write_all(&[0x81, 1, 0xc0]).await?;
+    assert!(matches!(
+        ws.recv().await?,
+        Event::Error(ProtocolError::InvalidUtf8)
+    ));
+
+    let mut buf = [0; 8];
+    server.read_exact(&mut buf).await?;
+    let code = [buf[2] ^ buf[6], buf[3] ^ buf[7]];
+    assert_eq!(u16::from_be_bytes(code), 1007);
+
+    let mut ws = WebSocket::client(ReadOnly(&[0x81, 5, b'H', b'e', b'l', b'l', b'o'][..]));
+    assert_eq!(ws.recv().await?.into_text().as_deref(), Some("Hello"));
+    Ok(())
+}